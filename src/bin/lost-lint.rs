@@ -0,0 +1,185 @@
+//! Checks `*.levels.ron` files for levels the game refuses to load and for mistakes the
+//! level loader would silently paper over.
+//! Classic `*.xsb` and `*.sok` collections are checked the way the game would load them.
+//!
+//! Usage: `lost-lint [--solve] <file>...`
+
+use std::{fmt, process::ExitCode};
+
+use lost::game::{
+    level::{LevelId, LevelLoaderError, StringLevel, StringLevels, TileKind},
+    puzzle::PuzzleState,
+    solver::{self, Solution, SolverConfig},
+    xsb::parse_xsb,
+};
+
+struct Problem {
+    /// Row and column counted from the top left of the level, both starting at 1.
+    pos: Option<(usize, usize)>,
+    kind: ProblemKind,
+}
+
+enum ProblemKind {
+    Load(LevelLoaderError),
+    NoBlocks,
+    OpenBorder,
+    WonAtSpawn,
+    Unwinnable,
+    SolverGaveUp { explored: usize },
+}
+
+impl fmt::Display for ProblemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProblemKind::Load(err) => write!(f, "{err}"),
+            ProblemKind::NoBlocks => write!(f, "level has no blocks"),
+            ProblemKind::OpenBorder => write!(f, "border is not a wall"),
+            ProblemKind::WonAtSpawn => write!(f, "level is already won at spawn"),
+            ProblemKind::Unwinnable => write!(f, "level can't be won"),
+            ProblemKind::SolverGaveUp { explored } => {
+                write!(f, "solver gave up after {explored} states")
+            }
+        }
+    }
+}
+
+fn lint_level(index: usize, string_level: &StringLevel, solve: bool) -> Vec<Problem> {
+    let level = match string_level.to_level(index) {
+        Ok(level) => level,
+        Err(err) => {
+            return vec![Problem {
+                pos: None,
+                kind: ProblemKind::Load(err),
+            }]
+        }
+    };
+
+    let mut problems = Vec::new();
+    let rows = level.to_rows();
+    for (r, row) in rows.iter().enumerate() {
+        for (c, glyph) in row.chars().enumerate() {
+            let border = r == 0 || r + 1 == rows.len() || c == 0 || c + 1 == row.len();
+            if border && glyph != TileKind::Wall.glyph() {
+                problems.push(Problem {
+                    pos: Some((r + 1, c + 1)),
+                    kind: ProblemKind::OpenBorder,
+                });
+            }
+        }
+    }
+    let has_blocks = level
+        .tiles
+        .iter()
+        .any(|tile| matches!(tile, TileKind::Pushable | TileKind::Pullable));
+    if !has_blocks {
+        problems.push(Problem {
+            pos: None,
+            kind: ProblemKind::NoBlocks,
+        });
+    }
+    // Simulating only makes sense on a level that is structurally sound.
+    if !problems.is_empty() {
+        return problems;
+    }
+
+    if PuzzleState::from_level(&level).is_some_and(|state| state.is_won()) {
+        problems.push(Problem {
+            pos: None,
+            kind: ProblemKind::WonAtSpawn,
+        });
+    } else if solve {
+        let report = solver::solve(&level, SolverConfig::default());
+        match report.solution {
+            Solution::Unwinnable => problems.push(Problem {
+                pos: None,
+                kind: ProblemKind::Unwinnable,
+            }),
+            Solution::BudgetExhausted => problems.push(Problem {
+                pos: None,
+                kind: ProblemKind::SolverGaveUp {
+                    explored: report.explored,
+                },
+            }),
+            Solution::Winnable(_) | Solution::Invalid => {}
+        }
+    }
+
+    problems
+}
+
+/// Returns whether the file passed all checks.
+fn lint_file(path: &str, solve: bool) -> bool {
+    let is_xsb = path.ends_with(".xsb") || path.ends_with(".sok");
+    let string_levels = match std::fs::read(path).map_err(Into::into).and_then(|bytes| {
+        if is_xsb {
+            Ok(parse_xsb(std::str::from_utf8(&bytes)?))
+        } else {
+            StringLevels::from_bytes(&bytes)
+        }
+    }) {
+        Ok(string_levels) => string_levels,
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            return false;
+        }
+    };
+
+    let ids = string_levels
+        .iter()
+        .enumerate()
+        .map(|(idx, string_level)| string_level.id(idx))
+        .collect::<Vec<LevelId>>();
+
+    let mut ok = true;
+    for (idx, string_level) in string_levels.iter().enumerate() {
+        let mut problems = lint_level(idx, string_level, solve);
+        if let Some(first) = ids[..idx].iter().position(|id| *id == ids[idx]) {
+            problems.push(Problem {
+                pos: None,
+                kind: ProblemKind::Load(LevelLoaderError::DuplicateId {
+                    id: ids[idx].clone(),
+                    first,
+                    second: idx,
+                }),
+            });
+        }
+        for problem in problems {
+            ok = false;
+            match (&problem.kind, problem.pos) {
+                // Loader errors name the level and position like the game shows them
+                (ProblemKind::Load(err), _) => println!("{path}: {err}"),
+                (kind, Some((row, col))) => {
+                    println!("{path}: level {}, row {row}, column {col}: {kind}", idx + 1)
+                }
+                (kind, None) => println!("{path}: level {}: {kind}", idx + 1),
+            }
+        }
+    }
+    ok
+}
+
+fn main() -> ExitCode {
+    let mut solve = false;
+    let mut paths = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--solve" => solve = true,
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("Usage: lost-lint [--solve] <file>...");
+        return ExitCode::from(2);
+    }
+
+    // Lint every file even if an earlier one already failed.
+    let results = paths
+        .iter()
+        .map(|path| lint_file(path, solve))
+        .collect::<Vec<bool>>();
+    if results.into_iter().all(|ok| ok) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use super::{
+    history::{HandleHistoryEvents, HistoryEvent},
+    player::{player_movement, Player, PlayerBumped, PlayerMoved},
+    Dir, GameState,
+};
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<PlayerAnimation>().add_systems(
+            Update,
+            (
+                start_animations
+                    .after(player_movement)
+                    .before(HandleHistoryEvents)
+                    .run_if(in_state(GameState::Play)),
+                animate_player
+                    .after(start_animations)
+                    .run_if(in_state(GameState::Play).or_else(in_state(GameState::Won))),
+            ),
+        );
+    }
+}
+
+/// Frames of every animation in the player sheet, one animation per row.
+pub const FRAMES: usize = 4;
+
+/// What the player is doing, each state has a row in the sheet for every [`Dir`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum AnimationState {
+    #[default]
+    Idle,
+    Walk,
+    Push,
+    Pull,
+    /// Walked into something that doesn't move.
+    Bump,
+}
+
+impl AnimationState {
+    fn frame_time(self) -> Duration {
+        match self {
+            AnimationState::Idle => Duration::from_millis(400),
+            AnimationState::Walk | AnimationState::Push | AnimationState::Pull => {
+                Duration::from_millis(30)
+            }
+            AnimationState::Bump => Duration::from_millis(40),
+        }
+    }
+
+    /// Only idle loops, everything else plays once and goes back to idle.
+    fn looping(self) -> bool {
+        matches!(self, AnimationState::Idle)
+    }
+}
+
+fn dir_row(dir: Dir) -> usize {
+    match dir {
+        Dir::Up => 0,
+        Dir::Right => 1,
+        Dir::Down => 2,
+        Dir::Left => 3,
+    }
+}
+
+#[derive(Component, Reflect, Clone, Debug)]
+pub struct PlayerAnimation {
+    pub state: AnimationState,
+    pub facing: Dir,
+    frame: usize,
+    timer: Timer,
+}
+
+impl Default for PlayerAnimation {
+    fn default() -> Self {
+        PlayerAnimation {
+            state: AnimationState::Idle,
+            facing: Dir::Down,
+            frame: 0,
+            timer: Timer::new(AnimationState::Idle.frame_time(), TimerMode::Repeating),
+        }
+    }
+}
+
+impl PlayerAnimation {
+    /// Restarts the animation, even when it is already playing.
+    pub fn play(&mut self, state: AnimationState, facing: Dir) {
+        self.state = state;
+        self.facing = facing;
+        self.frame = 0;
+        self.timer = Timer::new(state.frame_time(), TimerMode::Repeating);
+    }
+
+    /// Index into the player sheet's texture atlas.
+    pub fn index(&self) -> usize {
+        ((self.state as usize * 4) + dir_row(self.facing)) * FRAMES + self.frame
+    }
+}
+
+fn start_animations(
+    mut player: Query<&mut PlayerAnimation, With<Player>>,
+    mut player_moved: EventReader<PlayerMoved>,
+    mut player_bumped: EventReader<PlayerBumped>,
+    mut history_events: EventReader<HistoryEvent>,
+) {
+    let Ok(mut animation) = player.get_single_mut() else {
+        return;
+    };
+
+    // Jumping through history shouldn't play the moves
+    if history_events
+        .read()
+        .any(|ev| !matches!(ev, HistoryEvent::Record))
+    {
+        let facing = animation.facing;
+        animation.play(AnimationState::Idle, facing);
+    }
+    for moved in player_moved.read() {
+        match (moved.pushed, moved.pulled) {
+            (true, _) => animation.play(AnimationState::Push, moved.direction),
+            // Pulling walks backwards, facing the block
+            (false, true) => animation.play(AnimationState::Pull, moved.direction.opposite()),
+            (false, false) => animation.play(AnimationState::Walk, moved.direction),
+        }
+    }
+    for PlayerBumped(direction) in player_bumped.read() {
+        animation.play(AnimationState::Bump, *direction);
+    }
+}
+
+fn animate_player(
+    mut player: Query<(&mut PlayerAnimation, &mut TextureAtlas), With<Player>>,
+    time: Res<Time>,
+) {
+    let Ok((mut animation, mut atlas)) = player.get_single_mut() else {
+        return;
+    };
+
+    animation.timer.tick(time.delta());
+    for _ in 0..animation.timer.times_finished_this_tick() {
+        if animation.frame + 1 < FRAMES {
+            animation.frame += 1;
+        } else if animation.state.looping() {
+            animation.frame = 0;
+        } else {
+            let facing = animation.facing;
+            animation.play(AnimationState::Idle, facing);
+            break;
+        }
+    }
+    let index = animation.index();
+    if atlas.index != index {
+        atlas.index = index;
+    }
+}
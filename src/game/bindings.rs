@@ -0,0 +1,340 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use bevy::{log, prelude::*};
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{player::PlayerAction, save::data_dir, Dir, GameAction};
+
+/// Overrides where the bindings are stored.
+pub const CONFIG_PATH_VAR: &str = "LOST_CONFIG_PATH";
+
+pub struct BindingsPlugin;
+
+impl Plugin for BindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConfigPath>()
+            .add_systems(PreStartup, load_bindings)
+            .add_systems(Update, apply_bindings.run_if(resource_changed::<Bindings>));
+    }
+}
+
+/// Location of the config file, `None` disables storing the bindings.
+#[derive(Resource, Debug, Clone)]
+pub struct ConfigPath(pub Option<PathBuf>);
+
+impl Default for ConfigPath {
+    fn default() -> Self {
+        let path = std::env::var_os(CONFIG_PATH_VAR)
+            .map(PathBuf::from)
+            .or_else(|| data_dir().map(|dir| dir.join("lost").join("config.ron")));
+        ConfigPath(path)
+    }
+}
+
+/// Input that can be bound to an action.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+    /// Left stick pushed in a direction.
+    Stick(Dir),
+}
+
+impl Binding {
+    fn insert_into<A: Actionlike>(self, input_map: &mut InputMap<A>, action: A) {
+        match self {
+            Binding::Key(key) => input_map.insert(action, key),
+            Binding::Button(button) => input_map.insert(action, button),
+            Binding::Stick(dir) => input_map.insert(
+                action,
+                match dir {
+                    Dir::Up => GamepadControlDirection::LEFT_UP,
+                    Dir::Right => GamepadControlDirection::LEFT_RIGHT,
+                    Dir::Down => GamepadControlDirection::LEFT_DOWN,
+                    Dir::Left => GamepadControlDirection::LEFT_LEFT,
+                },
+            ),
+        };
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => {
+                let name = format!("{:?}", key);
+                write!(f, "{}", name.strip_prefix("Key").unwrap_or(&name))
+            }
+            Binding::Button(button) => write!(f, "Pad {:?}", button),
+            Binding::Stick(dir) => write!(f, "Stick {:?}", dir),
+        }
+    }
+}
+
+/// What every [`PlayerAction`] and [`GameAction`] is bound to, stored in the config file.
+///
+/// Actions missing from the file keep their default bindings.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Bindings {
+    pub player: BTreeMap<PlayerAction, Vec<Binding>>,
+    pub game: BTreeMap<GameAction, Vec<Binding>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::*;
+        use GameAction::*;
+        use GamepadButtonType as Pad;
+        use PlayerAction::*;
+
+        let player = BTreeMap::from([
+            (
+                Up,
+                vec![
+                    Key(KeyCode::KeyW),
+                    Key(KeyCode::ArrowUp),
+                    Button(Pad::DPadUp),
+                    Stick(Dir::Up),
+                ],
+            ),
+            (
+                Right,
+                vec![
+                    Key(KeyCode::KeyD),
+                    Key(KeyCode::ArrowRight),
+                    Button(Pad::DPadRight),
+                    Stick(Dir::Right),
+                ],
+            ),
+            (
+                Down,
+                vec![
+                    Key(KeyCode::KeyS),
+                    Key(KeyCode::ArrowDown),
+                    Button(Pad::DPadDown),
+                    Stick(Dir::Down),
+                ],
+            ),
+            (
+                Left,
+                vec![
+                    Key(KeyCode::KeyA),
+                    Key(KeyCode::ArrowLeft),
+                    Button(Pad::DPadLeft),
+                    Stick(Dir::Left),
+                ],
+            ),
+        ]);
+        let game = BTreeMap::from([
+            (Undo, vec![Key(KeyCode::KeyE), Button(Pad::West)]),
+            (Redo, vec![Key(KeyCode::KeyQ), Button(Pad::North)]),
+            (Reset, vec![Key(KeyCode::KeyR), Button(Pad::Select)]),
+            (ToLevelSelect, vec![Key(KeyCode::KeyG), Button(Pad::Start)]),
+            (ToEditor, vec![Key(KeyCode::Tab)]),
+            (CopySolution, vec![Key(KeyCode::KeyC)]),
+            (PasteSolution, vec![Key(KeyCode::KeyV)]),
+            (ToggleMute, vec![Key(KeyCode::KeyM)]),
+            (VolumeDown, vec![Key(KeyCode::Minus)]),
+            (VolumeUp, vec![Key(KeyCode::Equal)]),
+        ]);
+        Bindings { player, game }
+    }
+}
+
+/// Action of either input map, so both can be rebound the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BoundAction {
+    Player(PlayerAction),
+    Game(GameAction),
+}
+
+impl fmt::Display for BoundAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoundAction::Player(action) => write!(f, "Move {:?}", action),
+            BoundAction::Game(action) => write!(f, "{:?}", action),
+        }
+    }
+}
+
+impl Bindings {
+    /// Every action in the order they are listed in the settings.
+    pub fn actions(&self) -> impl Iterator<Item = BoundAction> + '_ {
+        self.player
+            .keys()
+            .copied()
+            .map(BoundAction::Player)
+            .chain(self.game.keys().copied().map(BoundAction::Game))
+    }
+
+    pub fn get(&self, action: BoundAction) -> &[Binding] {
+        match action {
+            BoundAction::Player(action) => self.player.get(&action),
+            BoundAction::Game(action) => self.game.get(&action),
+        }
+        .map_or(&[], Vec::as_slice)
+    }
+
+    /// Binds the action to `binding` in place of `replacing`, or next to its other bindings
+    /// when it doesn't replace one.
+    ///
+    /// The binding is taken away from every other action, including those of the other input
+    /// map, so one input never triggers two of them.
+    pub fn rebind(&mut self, action: BoundAction, replacing: Option<Binding>, binding: Binding) {
+        fn bind<A: Ord>(
+            map: &mut BTreeMap<A, Vec<Binding>>,
+            action: A,
+            replacing: Option<Binding>,
+            binding: Binding,
+        ) {
+            let bindings = map.entry(action).or_default();
+            match replacing.and_then(|old| bindings.iter().position(|bound| *bound == old)) {
+                Some(index) => bindings[index] = binding,
+                None => bindings.push(binding),
+            }
+        }
+
+        if replacing == Some(binding) {
+            return;
+        }
+        for bindings in self.player.values_mut().chain(self.game.values_mut()) {
+            bindings.retain(|bound| *bound != binding);
+        }
+        match action {
+            BoundAction::Player(action) => bind(&mut self.player, action, replacing, binding),
+            BoundAction::Game(action) => bind(&mut self.game, action, replacing, binding),
+        }
+    }
+
+    pub fn player_map(&self) -> InputMap<PlayerAction> {
+        input_map(&self.player)
+    }
+
+    pub fn game_map(&self) -> InputMap<GameAction> {
+        input_map(&self.game)
+    }
+
+    pub fn load(path: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        match ron::from_str::<Bindings>(&contents) {
+            Ok(mut bindings) => {
+                // Actions added since the file was written
+                let defaults = Bindings::default();
+                for (action, default) in defaults.player {
+                    bindings.player.entry(action).or_insert(default);
+                }
+                for (action, default) in defaults.game {
+                    bindings.game.entry(action).or_insert(default);
+                }
+                Some(bindings)
+            }
+            Err(err) => {
+                log::error!(
+                    "Could not parse config file {}, using default bindings: {}",
+                    path.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    pub fn write(&self, path: &Path) {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|contents| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+                }
+                std::fs::write(path, contents).map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            log::error!("Could not write config file {}: {}", path.display(), err);
+        }
+    }
+}
+
+fn input_map<A: Actionlike + Copy>(bindings: &BTreeMap<A, Vec<Binding>>) -> InputMap<A> {
+    let mut input_map = InputMap::default();
+    for (action, bindings) in bindings {
+        for binding in bindings {
+            binding.insert_into(&mut input_map, *action);
+        }
+    }
+    input_map
+}
+
+fn load_bindings(mut cmds: Commands, config_path: Res<ConfigPath>) {
+    let bindings = config_path
+        .0
+        .as_ref()
+        .and_then(|path| Bindings::load(path))
+        .unwrap_or_default();
+    cmds.insert_resource(bindings);
+}
+
+/// Replaces the input maps whenever the bindings change, including when they are loaded.
+fn apply_bindings(
+    bindings: Res<Bindings>,
+    mut player_maps: Query<&mut InputMap<PlayerAction>>,
+    mut game_maps: Query<&mut InputMap<GameAction>>,
+) {
+    for mut input_map in player_maps.iter_mut() {
+        *input_map = bindings.player_map();
+    }
+    for mut input_map in game_maps.iter_mut() {
+        *input_map = bindings.game_map();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebind_takes_binding_from_other_map() {
+        let mut bindings = Bindings::default();
+        let undo = Binding::Key(KeyCode::KeyE);
+        bindings.rebind(
+            BoundAction::Player(PlayerAction::Up),
+            Some(Binding::Key(KeyCode::KeyW)),
+            undo,
+        );
+
+        assert_eq!(
+            bindings.get(BoundAction::Player(PlayerAction::Up)),
+            &[
+                undo,
+                Binding::Key(KeyCode::ArrowUp),
+                Binding::Button(GamepadButtonType::DPadUp),
+                Binding::Stick(Dir::Up),
+            ]
+        );
+        assert_eq!(
+            bindings.get(BoundAction::Game(GameAction::Undo)),
+            &[Binding::Button(GamepadButtonType::West)]
+        );
+    }
+
+    #[test]
+    fn rebind_adds_next_to_other_bindings() {
+        let mut bindings = Bindings::default();
+        let south = Binding::Button(GamepadButtonType::South);
+        bindings.rebind(BoundAction::Game(GameAction::Undo), None, south);
+
+        assert_eq!(
+            bindings.get(BoundAction::Game(GameAction::Undo)),
+            &[
+                Binding::Key(KeyCode::KeyE),
+                Binding::Button(GamepadButtonType::West),
+                south,
+            ]
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use leafwing_input_manager::prelude::*;
+
+use super::{
+    collision::CollisionMap,
+    history::HistoryEvent,
+    player::{player_input, MoveQueue, Player, PlayerAction, PlayerInput},
+    puzzle::Board,
+    replay::Replay,
+    util::{viewport_tile, CARDINALS_DIR},
+    Dir, EntityKind, GameAction, GameState, TilePos,
+};
+
+pub struct ClickMovePlugin;
+
+impl Plugin for ClickMovePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WalkPath>()
+            .add_systems(
+                OnTransition {
+                    exited: GameState::LevelTransition,
+                    entered: GameState::Play,
+                },
+                clear_walk_path,
+            )
+            .add_systems(
+                Update,
+                click_to_move
+                    .in_set(PlayerInput)
+                    .before(player_input)
+                    .run_if(not(resource_exists::<Replay>))
+                    .run_if(in_state(GameState::Play)),
+            );
+    }
+}
+
+/// Moves left of a walk to a clicked tile, they are queued one by one so each is its own turn.
+#[derive(Resource, Default, Debug, Deref, DerefMut)]
+pub struct WalkPath(pub VecDeque<Dir>);
+
+fn clear_walk_path(mut path: ResMut<WalkPath>) {
+    path.clear();
+}
+
+/// Position of a click or tap that started this frame.
+fn pressed_position(
+    mouse: &ButtonInput<MouseButton>,
+    touches: &Touches,
+    window: &Query<&Window, With<PrimaryWindow>>,
+) -> Option<Vec2> {
+    if mouse.just_pressed(MouseButton::Left) {
+        return window.get_single().ok()?.cursor_position();
+    }
+    touches
+        .iter_just_pressed()
+        .next()
+        .map(|touch| touch.position())
+}
+
+/// Walks to a clicked floor tile along the shortest path, or pushes a clicked block next to
+/// the player. Any key press stops the walk, as does any bound input like the stick.
+#[allow(clippy::too_many_arguments)]
+fn click_to_move(
+    mut path: ResMut<WalkPath>,
+    mut queue: ResMut<MoveQueue>,
+    mut history_events: EventReader<HistoryEvent>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    player_actions: Query<&ActionState<PlayerAction>>,
+    game_actions: Query<&ActionState<GameAction>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    ui: Query<&Interaction>,
+    player: Query<&TilePos, With<Player>>,
+    collision: Res<CollisionMap>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+
+    let undone = history_events
+        .read()
+        .any(|ev| !matches!(ev, HistoryEvent::Record));
+    let pressed = keys.get_just_pressed().next().is_some()
+        || player_actions
+            .iter()
+            .any(|actions| !actions.get_just_pressed().is_empty())
+        || game_actions
+            .iter()
+            .any(|actions| !actions.get_just_pressed().is_empty());
+    if !path.is_empty() && (undone || pressed) {
+        path.clear();
+        queue.clear();
+    }
+
+    // Clicks on buttons, like the timeline, are handled by them
+    let over_ui = ui
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+    if let Some(target) = pressed_position(&mouse, &touches, &window)
+        .filter(|_| !over_ui)
+        .and_then(|pos| viewport_tile(&camera, pos))
+    {
+        let adjacent = CARDINALS_DIR
+            .into_iter()
+            .find(|dir| **player + IVec2::from(*dir) == target);
+        let walk = match (adjacent, collision.kind_at(target)) {
+            (Some(dir), Some(Some(EntityKind::Pushable))) => Some(vec![dir]),
+            _ => collision.path(**player, target),
+        };
+        if let Some(walk) = walk.filter(|walk| !walk.is_empty()) {
+            queue.clear();
+            **path = walk.into();
+        }
+    }
+
+    if queue.is_empty() {
+        if let Some(dir) = path.pop_front() {
+            queue.push(dir);
+        }
+    }
+}
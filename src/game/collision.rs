@@ -3,7 +3,9 @@ use bevy::{log, prelude::*};
 use crate::grid::Grid;
 
 use super::{
-    level::Levels, level_select::CurrentLevel, Dir, EntityKind, GameAssets, GameState, TilePos,
+    level::LevelData,
+    puzzle::{Board, Push},
+    Dir, EntityKind, GameState, TilePos,
 };
 
 pub struct CollisionPlugin;
@@ -47,18 +49,10 @@ impl Default for CollisionMap {
 
 pub fn init_collision_map(
     mut cmds: Commands,
-    current_level: Res<CurrentLevel>,
-    levels: Res<Assets<Levels>>,
-    assets: Res<GameAssets>,
+    level_data: LevelData,
     sokoban_entities: Query<(Entity, &TilePos, &EntityKind)>,
 ) {
-    // TODO Make some system param for this
-    let size = levels
-        .get(&assets.levels)
-        .expect("Level assets should be loaded")
-        .get(**current_level)
-        .expect("Current level will always be a valid level index")
-        .size;
+    let size = level_data.size();
     log::debug!("Initialized collision map");
     let mut map = Grid::new(
         IVec2::new(size.x as i32, size.y as i32),
@@ -96,26 +90,34 @@ fn sync_collision_map(
 }
 
 pub enum CollisionResult {
-    Push(Vec<Entity>),
+    /// `moving` contains every entity that moves, including the pusher.
+    Push {
+        moving: Vec<Entity>,
+        push: Push,
+    },
     Collision,
 }
 
-impl CollisionMap {
-    pub fn is_blocked(&self, pos: IVec2, player: bool) -> bool {
-        self.get(pos).map_or(true, |entry| match entry {
-            CollisionEntry::Free => false,
-            CollisionEntry::Occupied { entity: _, kind } => {
-                matches!(
-                    kind,
-                    EntityKind::Wall | EntityKind::Pullable | EntityKind::Pushable
-                ) || if player {
-                    matches!(kind, EntityKind::Pit)
-                } else {
-                    matches!(kind, EntityKind::Platform)
-                }
-            }
+impl Board for CollisionMap {
+    fn size(&self) -> IVec2 {
+        self.0.size()
+    }
+
+    fn kind_at(&self, pos: IVec2) -> Option<Option<EntityKind>> {
+        self.get(pos).map(|entry| match entry {
+            CollisionEntry::Free => None,
+            CollisionEntry::Occupied { entity: _, kind } => Some(*kind),
         })
     }
+}
+
+impl CollisionMap {
+    pub fn entity_at(&self, pos: IVec2) -> Option<Entity> {
+        match self.get(pos)? {
+            CollisionEntry::Free => None,
+            CollisionEntry::Occupied { entity, kind: _ } => Some(*entity),
+        }
+    }
 
     pub fn player_push_collision(
         &self,
@@ -123,37 +125,18 @@ impl CollisionMap {
         pusher_pos: IVec2,
         direction: Dir,
     ) -> CollisionResult {
-        let move_in_dir = |pos, dir| -> IVec2 { pos + IVec2::from(dir) };
-        let dest = move_in_dir(pusher_pos, direction);
-        let mut moving_entities = Vec::new();
-        if let Some(CollisionEntry::Occupied {
-            entity: pushed,
-            kind,
-        }) = self.get(dest)
-        {
-            match kind {
-                EntityKind::Pushable => {
-                    if self.is_blocked(move_in_dir(dest, direction), false) {
-                        return CollisionResult::Collision;
-                    } else {
-                        moving_entities.push(*pushed)
-                    }
-                }
-                EntityKind::Wall | EntityKind::Pullable | EntityKind::Pit => {
-                    return CollisionResult::Collision
-                }
-                EntityKind::Platform => {}
-            }
-        }
-        let opp = move_in_dir(pusher_pos, direction.opposite());
-        if !self.is_blocked(pusher_pos, false) {
-            if let Some(CollisionEntry::Occupied { entity, kind }) = self.get(opp) {
-                if matches!(kind, EntityKind::Pullable) {
-                    moving_entities.push(*entity);
-                }
-            }
-        }
+        let Some(push) = self.push(pusher_pos, direction) else {
+            return CollisionResult::Collision;
+        };
+        let mut moving_entities = [push.pushed, push.pulled]
+            .into_iter()
+            .flatten()
+            .filter_map(|pos| self.entity_at(pos))
+            .collect::<Vec<Entity>>();
         moving_entities.push(pusher);
-        CollisionResult::Push(moving_entities)
+        CollisionResult::Push {
+            moving: moving_entities,
+            push,
+        }
     }
 }
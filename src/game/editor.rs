@@ -0,0 +1,426 @@
+use bevy::{log, prelude::*, window::PrimaryWindow};
+use bevy_simple_tilemap::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    cleanup::{cleanup_all_with, DependOnState},
+    grid::Grid,
+};
+
+use super::{
+    level::{has_comments, Level, LevelData, LevelLoader, Levels, TileKind},
+    level_select::CurrentLevel,
+    pack::CurrentPack,
+    puzzle::PuzzleState,
+    util::cursor_tile,
+    GameAssets, GameState, TilePos,
+};
+
+const CURRENT_STATE: GameState = GameState::Editor;
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(InputManagerPlugin::<EditorAction>::default())
+            .init_resource::<Brush>()
+            .add_systems(Startup, setup)
+            .add_systems(OnEnter(CURRENT_STATE), (init_editor_level, spawn_editor_ui))
+            .add_systems(
+                Update,
+                (
+                    select_brush,
+                    paint,
+                    resize,
+                    play_test,
+                    #[cfg(not(target_arch = "wasm32"))]
+                    save,
+                    (
+                        (cleanup_all_with::<EditorView>, draw_level)
+                            .chain()
+                            .run_if(resource_exists_and_changed::<EditorLevel>),
+                        update_ui,
+                    ),
+                )
+                    .chain()
+                    .run_if(in_state(CURRENT_STATE)),
+            );
+    }
+}
+
+#[derive(Actionlike, Clone, Copy, Hash, Debug, PartialEq, Eq, Reflect)]
+pub enum EditorAction {
+    Paint,
+    Erase,
+    BrushWall,
+    BrushFloor,
+    BrushPlayer,
+    BrushPushable,
+    BrushPullable,
+    BrushPlatform,
+    BrushPit,
+    Wider,
+    Narrower,
+    Taller,
+    Shorter,
+    PlayTest,
+    Save,
+}
+
+impl EditorAction {
+    pub fn brush(&self) -> Option<TileKind> {
+        match self {
+            EditorAction::BrushWall => Some(TileKind::Wall),
+            EditorAction::BrushFloor => Some(TileKind::Floor),
+            EditorAction::BrushPlayer => Some(TileKind::Player),
+            EditorAction::BrushPushable => Some(TileKind::Pushable),
+            EditorAction::BrushPullable => Some(TileKind::Pullable),
+            EditorAction::BrushPlatform => Some(TileKind::Platform),
+            EditorAction::BrushPit => Some(TileKind::Pit),
+            _ => None,
+        }
+    }
+}
+
+fn editor_actions() -> InputMap<EditorAction> {
+    use EditorAction::*;
+    let mut input_map = InputMap::default();
+
+    input_map.insert(Paint, MouseButton::Left);
+    input_map.insert(Erase, MouseButton::Right);
+    input_map.insert(BrushWall, KeyCode::Digit1);
+    input_map.insert(BrushFloor, KeyCode::Digit2);
+    input_map.insert(BrushPlayer, KeyCode::Digit3);
+    input_map.insert(BrushPushable, KeyCode::Digit4);
+    input_map.insert(BrushPullable, KeyCode::Digit5);
+    input_map.insert(BrushPlatform, KeyCode::Digit6);
+    input_map.insert(BrushPit, KeyCode::Digit7);
+    input_map.insert(Wider, KeyCode::ArrowRight);
+    input_map.insert(Narrower, KeyCode::ArrowLeft);
+    input_map.insert(Taller, KeyCode::ArrowUp);
+    input_map.insert(Shorter, KeyCode::ArrowDown);
+    input_map.insert(PlayTest, KeyCode::Tab);
+    input_map.insert(Save, KeyCode::F5);
+
+    input_map
+}
+
+fn setup(mut cmds: Commands) {
+    cmds.spawn((
+        (InputManagerBundle::<EditorAction> {
+            input_map: editor_actions(),
+            ..default()
+        },),
+        Name::new("EditorActions"),
+    ));
+}
+
+/// Copy of the current level that is being edited.
+#[derive(Resource, Deref, DerefMut)]
+pub struct EditorLevel(pub Grid<TileKind>);
+
+impl EditorLevel {
+    pub fn from_level(level: &Level) -> Self {
+        Self(Grid::from_raw(level.size.as_ivec2(), level.tiles.clone()))
+    }
+
+    /// Replaces the layout of `level` with the edited one.
+    pub fn apply_to(&self, level: &mut Level) {
+        level.tiles = self.raw().to_vec();
+        level.size = self.size().as_uvec2();
+    }
+}
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct Brush(pub TileKind);
+
+impl Default for Brush {
+    fn default() -> Self {
+        Brush(TileKind::Wall)
+    }
+}
+
+#[derive(Component)]
+struct EditorView;
+
+#[derive(Component)]
+struct BrushText;
+
+fn init_editor_level(mut cmds: Commands, level_data: LevelData) {
+    cmds.insert_resource(EditorLevel::from_level(level_data.current_level_data()));
+}
+
+fn spawn_editor_ui(mut cmds: Commands) {
+    cmds.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.),
+                left: Val::Px(10.),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        },
+        DependOnState::single(CURRENT_STATE),
+        Name::new("Editor UI"),
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 24.,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ),
+            BrushText,
+        ));
+        parent.spawn(TextBundle::from_section(
+            "[1-7] Brush  [LMB] Paint  [RMB] Erase  [Arrows] Resize  [Tab] Play  [F5] Save",
+            TextStyle {
+                font_size: 16.,
+                color: Color::WHITE,
+                ..default()
+            },
+        ));
+    });
+}
+
+fn select_brush(actions: Query<&ActionState<EditorAction>>, mut brush: ResMut<Brush>) {
+    let Ok(actions) = actions.get_single() else {
+        return;
+    };
+
+    if let Some(kind) = actions
+        .get_just_pressed()
+        .iter()
+        .find_map(|action| action.brush())
+    {
+        **brush = kind;
+    }
+}
+
+fn paint(
+    actions: Query<&ActionState<EditorAction>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    brush: Res<Brush>,
+    mut editor_level: ResMut<EditorLevel>,
+) {
+    let Ok(actions) = actions.get_single() else {
+        return;
+    };
+    let kind = if actions.pressed(&EditorAction::Paint) {
+        **brush
+    } else if actions.pressed(&EditorAction::Erase) {
+        TileKind::Floor
+    } else {
+        return;
+    };
+    let Some(pos) = cursor_tile(window, camera) else {
+        return;
+    };
+    // Avoid triggering change detection every frame the button is held
+    if editor_level.get(pos).is_none_or(|tile| *tile == kind) {
+        return;
+    }
+
+    // There can only be one player
+    if matches!(kind, TileKind::Player) {
+        editor_level
+            .iter_mut()
+            .filter(|(_, tile)| matches!(tile, TileKind::Player))
+            .for_each(|(_, tile)| *tile = TileKind::Floor);
+    }
+    editor_level.set(pos, kind);
+}
+
+fn resize(actions: Query<&ActionState<EditorAction>>, mut editor_level: ResMut<EditorLevel>) {
+    let Ok(actions) = actions.get_single() else {
+        return;
+    };
+
+    let mut size = editor_level.size();
+    if actions.just_pressed(&EditorAction::Wider) {
+        size.x += 1;
+    }
+    if actions.just_pressed(&EditorAction::Narrower) {
+        size.x -= 1;
+    }
+    if actions.just_pressed(&EditorAction::Taller) {
+        size.y += 1;
+    }
+    if actions.just_pressed(&EditorAction::Shorter) {
+        size.y -= 1;
+    }
+    let size = size.max(IVec2::ONE);
+
+    if size != editor_level.size() {
+        let resized = Grid::populate_from(size, |pos| {
+            editor_level.get(pos).copied().unwrap_or(TileKind::Wall)
+        });
+        **editor_level = resized;
+    }
+}
+
+/// Checks the edited layout on a copy, so an unplayable layout never replaces the loaded level.
+fn is_playable(editor_level: &EditorLevel) -> bool {
+    let mut edited = Level::default();
+    editor_level.apply_to(&mut edited);
+    let playable = PuzzleState::from_level(&edited).is_some();
+    if !playable {
+        log::warn!("Level needs exactly one player to be played");
+    }
+    playable
+}
+
+fn play_test(
+    actions: Query<&ActionState<EditorAction>>,
+    editor_level: Res<EditorLevel>,
+    current_level: Res<CurrentLevel>,
+    mut levels: ResMut<Assets<Levels>>,
+    current_pack: Res<CurrentPack>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Ok(actions) = actions.get_single() else {
+        return;
+    };
+    if !actions.just_pressed(&EditorAction::PlayTest) {
+        return;
+    }
+
+    if !is_playable(&editor_level) {
+        return;
+    }
+    let levels = levels
+        .get_mut(&current_pack.levels)
+        .expect("Level assets should be loaded");
+    let level = levels
+        .get_by_id_mut(&current_level)
+        .expect("Current level is resolved before it is edited");
+    editor_level.apply_to(level);
+    next_state.set(GameState::LevelTransition);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save(
+    actions: Query<&ActionState<EditorAction>>,
+    editor_level: Res<EditorLevel>,
+    current_level: Res<CurrentLevel>,
+    mut levels: ResMut<Assets<Levels>>,
+    current_pack: Res<CurrentPack>,
+    asset_server: Res<AssetServer>,
+) {
+    let Ok(actions) = actions.get_single() else {
+        return;
+    };
+    if !actions.just_pressed(&EditorAction::Save) {
+        return;
+    }
+
+    let Some(path) = asset_server.get_path(&current_pack.levels) else {
+        log::warn!("Levels were not loaded from a file");
+        return;
+    };
+    // Saving writes ron, which other formats like xsb can't be read back from
+    if !LevelLoader::handles(path.path()) {
+        log::warn!("Only levels.ron files can be saved, not {}", path);
+        return;
+    }
+    // The loader rejects the whole pack if one of its levels is broken
+    if !is_playable(&editor_level) {
+        return;
+    }
+    let levels = levels
+        .get_mut(&current_pack.levels)
+        .expect("Level assets should be loaded");
+    editor_level.apply_to(
+        levels
+            .get_by_id_mut(&current_level)
+            .expect("Current level is resolved before it is edited"),
+    );
+
+    let file = bevy::asset::io::file::FileAssetReader::get_base_path()
+        .join("assets")
+        .join(path.path());
+    // The file is written from scratch, so hand-written comments are kept in a backup
+    if std::fs::read_to_string(&file).is_ok_and(|contents| has_comments(&contents)) {
+        let backup = file.with_extension("ron.bak");
+        if let Err(err) = std::fs::copy(&file, &backup) {
+            log::error!("Could not back up {}, not saving: {}", file.display(), err);
+            return;
+        }
+        log::warn!(
+            "Comments are not saved, the previous {} is kept as {}",
+            file.display(),
+            backup.display()
+        );
+    }
+    match std::fs::write(&file, levels.to_ron()) {
+        Ok(()) => log::info!("Saved levels to {}", file.display()),
+        Err(err) => log::error!("Could not save levels to {}: {}", file.display(), err),
+    }
+}
+
+fn draw_level(mut cmds: Commands, editor_level: Res<EditorLevel>, assets: Res<GameAssets>) {
+    let mut level = Level::default();
+    editor_level.apply_to(&mut level);
+
+    let mut tiles = Vec::new();
+    for (pos, tile) in editor_level.iter() {
+        let pos = TilePos(pos);
+        let (sprite_index, flags) = tile.index_flip(&pos, &level);
+        tiles.push((
+            pos.extend(0),
+            Some(Tile {
+                sprite_index,
+                flags,
+                ..default()
+            }),
+        ));
+
+        let texture = match tile {
+            TileKind::Player => &assets.player,
+            TileKind::Pushable => &assets.pushable,
+            TileKind::Pullable => &assets.pullable,
+            _ => continue,
+        };
+        cmds.spawn((
+            SpriteBundle {
+                texture: texture.clone_weak(),
+                transform: Transform::from_translation(pos.wpos().extend(1.)),
+                ..default()
+            },
+            EditorView,
+            DependOnState::single(CURRENT_STATE),
+        ));
+    }
+
+    let mut tilemap = TileMap::default();
+    tilemap.set_tiles(tiles);
+    cmds.spawn((
+        TileMapBundle {
+            tilemap,
+            atlas: assets.layout.clone_weak().into(),
+            texture: assets.tiles.clone_weak(),
+            ..default()
+        },
+        EditorView,
+        DependOnState::single(CURRENT_STATE),
+        Name::new("Editor Level"),
+    ));
+}
+
+fn update_ui(
+    brush: Res<Brush>,
+    editor_level: Res<EditorLevel>,
+    mut text: Query<&mut Text, With<BrushText>>,
+) {
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    let size = editor_level.size();
+    text.sections[0].value = format!("Brush: {:?}  Size: {}x{}", **brush, size.x, size.y);
+}
@@ -1,6 +1,6 @@
-use std::{marker::PhantomData, ops::AddAssign};
+use std::marker::PhantomData;
 
-use bevy::prelude::*;
+use bevy::{log, prelude::*};
 
 use super::GameState;
 
@@ -9,8 +9,11 @@ pub struct HistoryPlugin;
 impl Plugin for HistoryPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<CurrentTime>()
+            .init_resource::<HistoryMode>()
             .register_type::<CurrentTime>()
+            .register_type::<HistoryMode>()
             .add_event::<HistoryEvent>()
+            .add_event::<JumpToTurn>()
             .add_systems(
                 OnTransition {
                     exited: GameState::LevelTransition,
@@ -33,7 +36,8 @@ impl<C: Component + Clone> Plugin for HistoryComponentPlugin<C> {
             Update,
             handle_history_events::<C>
                 .in_set(HandleHistoryEvents)
-                .before(handle_time),
+                .before(handle_time)
+                .run_if(resource_equals(HistoryMode::Components)),
         );
     }
 }
@@ -57,22 +61,87 @@ impl<C: Component + Clone> Plugin for PreviousComponentPlugin<C> {
 #[derive(SystemSet, Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
 pub struct HandleHistoryEvents;
 
+/// Set to `snapshots` to keep the history in [`HistoryMode::Snapshots`].
+pub const HISTORY_MODE_VAR: &str = "LOST_HISTORY_MODE";
+
+/// How the state of earlier turns is kept, only change this between levels.
+#[derive(Resource, Reflect, Copy, Clone, Debug, PartialEq, Eq)]
+#[reflect(Resource)]
+pub enum HistoryMode {
+    /// Every entity keeps its own [`History`], despawns are tracked in
+    /// [`DespawnHistory`](super::mechanics::DespawnHistory).
+    Components,
+    /// The whole board is stored every turn, see [`Snapshots`](super::snapshot::Snapshots).
+    Snapshots,
+}
+
+impl Default for HistoryMode {
+    fn default() -> Self {
+        match std::env::var(HISTORY_MODE_VAR).as_deref() {
+            Ok("snapshots") => HistoryMode::Snapshots,
+            Ok("components") | Err(_) => HistoryMode::Components,
+            Ok(other) => {
+                log::warn!(
+                    "Unknown {} {:?}, expected components or snapshots",
+                    HISTORY_MODE_VAR,
+                    other
+                );
+                HistoryMode::Components
+            }
+        }
+    }
+}
+
 #[derive(SystemSet, Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
 pub struct HandlePreviousComponent;
 
 #[derive(Resource, Reflect, Default, Copy, Clone, Debug, Deref, DerefMut)]
 #[reflect(Resource)]
-pub struct CurrentTime(pub usize);
+pub struct CurrentTime {
+    #[deref]
+    pub now: usize,
+    /// Time before the last undo, reachable again with redo.
+    pub latest: usize,
+}
+
+impl CurrentTime {
+    pub fn can_redo(&self) -> bool {
+        self.now < self.latest
+    }
+
+    /// Advances the time the way it changes after the event was handled.
+    pub fn apply(&mut self, ev: &HistoryEvent) {
+        match ev {
+            HistoryEvent::Record | HistoryEvent::Reset => {
+                self.now += 1;
+                // A new step replaces everything that could have been redone
+                self.latest = self.now;
+            }
+            HistoryEvent::Rewind => self.now = self.now.saturating_sub(1),
+            HistoryEvent::Redo => {
+                if self.can_redo() {
+                    self.now += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Goes back or forward to the given turn, turn 0 being the start of the level.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct JumpToTurn(pub usize);
 
 #[derive(Event)]
 pub enum HistoryEvent {
     Record,
     Rewind,
+    /// Does the last undone step again, does nothing if there is none.
+    Redo,
     Reset,
 }
 
 fn reset_time(mut current_time: ResMut<CurrentTime>) {
-    **current_time = 0;
+    *current_time = CurrentTime::default();
 }
 
 fn handle_time(
@@ -80,11 +149,24 @@ fn handle_time(
     mut history_events: EventReader<HistoryEvent>,
 ) {
     for ev in history_events.read() {
-        match ev {
-            HistoryEvent::Record => current_time.add_assign(1),
-            HistoryEvent::Rewind => **current_time = current_time.saturating_sub(1),
-            HistoryEvent::Reset => current_time.add_assign(1),
-        }
+        current_time.apply(ev);
+    }
+}
+
+/// Turns a [`JumpToTurn`] into the undos or redos that lead there.
+pub fn jump_to_turn(
+    mut jumps: EventReader<JumpToTurn>,
+    mut history_events: EventWriter<HistoryEvent>,
+    current_time: Res<CurrentTime>,
+) {
+    let Some(JumpToTurn(turn)) = jumps.read().last().copied() else {
+        return;
+    };
+    let turn = turn.min(current_time.latest);
+    if turn < current_time.now {
+        history_events.send_batch((turn..current_time.now).map(|_| HistoryEvent::Rewind));
+    } else {
+        history_events.send_batch((current_time.now..turn).map(|_| HistoryEvent::Redo));
     }
 }
 
@@ -95,7 +177,12 @@ pub struct HistoryBundle<C: Component + Clone> {
 }
 
 #[derive(Component, Clone, Default, Deref, DerefMut, Reflect)]
-pub struct History<C: Component + Clone>(Vec<(usize, C)>);
+pub struct History<C: Component + Clone> {
+    #[deref]
+    past: Vec<(usize, C)>,
+    /// Undone values, the last one is redone first.
+    redo: Vec<(usize, C)>,
+}
 
 #[derive(Component, Clone, Default, Deref, DerefMut, Reflect)]
 pub struct PreviousComponent<C: Component + Clone>(C);
@@ -121,19 +208,23 @@ fn handle_history_events<C>(
 ) where
     C: Component + Clone,
 {
+    // Several events can arrive in one frame, the time is only updated afterwards
+    let mut now = *current_time;
     for ev in history_events.read() {
         match ev {
             HistoryEvent::Record => {
                 for (mut history, _, prev) in history_query.iter_mut() {
-                    history.push((**current_time, prev.get().clone()));
+                    history.push((*now, prev.get().clone()));
+                    history.redo.clear();
                 }
             }
             HistoryEvent::Rewind => {
                 for (mut history, mut component, _) in history_query.iter_mut() {
                     if let Some((t, _)) = history.last() {
-                        if (t + 1) == **current_time {
-                            let (_, prev_component) = history.pop().unwrap();
-                            *component = prev_component;
+                        if (t + 1) == *now {
+                            let (t, prev_component) = history.pop().unwrap();
+                            let undone = std::mem::replace(&mut *component, prev_component);
+                            history.redo.push((t, undone));
                         }
                     }
                 }
@@ -142,11 +233,22 @@ fn handle_history_events<C>(
                 for (mut history, mut component, _) in history_query.iter_mut() {
                     if let Some(first) = history.first() {
                         let first_component = first.1.clone();
-                        history.push((**current_time, component.clone()));
+                        history.push((*now, component.clone()));
+                        history.redo.clear();
                         *component = first_component;
                     }
                 }
             }
+            HistoryEvent::Redo => {
+                for (mut history, mut component, _) in history_query.iter_mut() {
+                    if history.redo.last().is_some_and(|(t, _)| *t == *now) {
+                        let (t, next_component) = history.redo.pop().unwrap();
+                        let redone = std::mem::replace(&mut *component, next_component);
+                        history.push((t, redone));
+                    }
+                }
+            }
         }
+        now.apply(ev);
     }
 }
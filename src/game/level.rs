@@ -1,7 +1,7 @@
-use std::future::Future;
+use std::{future::Future, path::Path};
 
 use bevy::{
-    asset::{AssetLoader, AsyncReadExt},
+    asset::{AssetLoadFailedEvent, AssetLoader, AsyncReadExt},
     ecs::system::SystemParam,
     prelude::*,
     reflect::TypePath,
@@ -9,14 +9,20 @@ use bevy::{
 };
 use bevy_simple_tilemap::prelude::*;
 use bevy_simple_tilemap::TileFlags;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::{cleanup::DependOnState, grid::Grid};
 
 use super::{
-    collision::init_collision_map, history::HistoryBundle, level_select::CurrentLevel,
-    player::SpawnPlayer, util::DIRS, EntityKind, GameAssets, GameState, TilePos,
+    collision::init_collision_map,
+    history::HistoryBundle,
+    level_select::CurrentLevel,
+    mechanics::BlockId,
+    pack::{CurrentPack, LevelPack, LevelPacks},
+    player::SpawnPlayer,
+    util::DIRS,
+    EntityKind, GameAssets, GameState, TilePos,
 };
 
 pub struct LevelPlugin;
@@ -36,9 +42,14 @@ impl Plugin for LevelPlugin {
         )
         .add_systems(
             Update,
-            reload_on_change
-                .run_if(in_state(GameState::Play))
-                .run_if(on_event::<AssetEvent<Levels>>()),
+            (
+                reload_on_change
+                    .run_if(in_state(GameState::Play))
+                    .run_if(on_event::<AssetEvent<Levels>>()),
+                report_load_errors::<Levels>.run_if(on_event::<AssetLoadFailedEvent<Levels>>()),
+                report_load_errors::<LevelPacks>
+                    .run_if(on_event::<AssetLoadFailedEvent<LevelPacks>>()),
+            ),
         );
     }
 }
@@ -46,25 +57,52 @@ impl Plugin for LevelPlugin {
 #[derive(SystemParam)]
 pub struct LevelData<'w> {
     current_level: Res<'w, CurrentLevel>,
+    current_pack: Res<'w, CurrentPack>,
     levels: Res<'w, Assets<Levels>>,
+    packs: Res<'w, Assets<LevelPacks>>,
     assets: Res<'w, GameAssets>,
 }
 
 impl<'w> LevelData<'w> {
+    pub fn packs(&self) -> &LevelPacks {
+        self.packs
+            .get(&self.assets.packs)
+            .expect("Pack index should be loaded")
+    }
+
+    pub fn current_pack(&self) -> &LevelPack {
+        self.packs()
+            .get_by_id(&self.current_pack.id)
+            .expect("Current pack should only ever be set to a listed pack")
+    }
+
+    /// Levels of the current pack.
     pub fn levels(&self) -> &Levels {
         self.levels
-            .get(&self.assets.levels)
+            .get(&self.current_pack.levels)
             .expect("Level handle should be loaded")
     }
 
     pub fn current_level_data(&self) -> &Level {
         self.levels()
-            .get(self.current_level())
-            .expect("Current level should only ever be set to a valid level")
+            .get(self.current_index())
+            .expect("Levels files contain at least one level")
     }
 
-    pub fn current_level(&self) -> usize {
-        **self.current_level
+    /// Id of the current level, see [`LevelData::current_index`].
+    pub fn current_level(&self) -> &LevelId {
+        &self.current_level_data().id
+    }
+
+    /// Position of the current level in the levels file.
+    /// Falls back to the first level if [`CurrentLevel`] doesn't name an existing level,
+    /// e.g. because it was removed from the file.
+    pub fn current_index(&self) -> usize {
+        self.levels().index_of(&self.current_level).unwrap_or(0)
+    }
+
+    pub fn next_level(&self) -> Option<&Level> {
+        self.levels().get(self.current_index() + 1)
     }
 
     pub fn size(&self) -> UVec2 {
@@ -79,14 +117,17 @@ impl<'w> LevelData<'w> {
 #[derive(Component)]
 pub struct LevelRoot;
 
+/// Tilemap with the floor, platforms and pits.
+#[derive(Component)]
+pub struct GroundTiles;
+
 fn spawn_level(mut cmds: Commands, level_data: LevelData, assets: Res<GameAssets>) {
     let level = level_data.current_level_data();
 
     let level_root = cmds
         .spawn((
             SpatialBundle::default(),
-            // DependOnState(vec![GameState::Play, GameState::Pause]),
-            DependOnState::single(GameState::Play),
+            DependOnState(vec![GameState::Play, GameState::Won]),
             Name::new("Level Root"),
             LevelRoot,
         ))
@@ -98,6 +139,7 @@ fn spawn_level(mut cmds: Commands, level_data: LevelData, assets: Res<GameAssets
     let mut tiles = Vec::new();
     let mut wall_tiles = Vec::new();
     let mut sub_wall_tiles = Vec::new();
+    let mut blocks = 0;
     for (idx, tile) in level.tiles.iter().enumerate() {
         let x = idx as i32 % level.size.x as i32;
         let y = idx as i32 / level.size.x as i32;
@@ -106,7 +148,12 @@ fn spawn_level(mut cmds: Commands, level_data: LevelData, assets: Res<GameAssets
         let (sprite_index, flags) = tile.index_flip(&pos, level);
 
         // Tile is not walkable and above us is static tile
-        if !tile.is_static() && level.tiles[idx + level.size.x as usize].is_static() {
+        if !tile.is_static()
+            && level
+                .tiles
+                .get(idx + level.size.x as usize)
+                .is_some_and(|above| above.is_static())
+        {
             sub_wall_tiles.push((
                 IVec3::new(x, y, y),
                 Some(Tile {
@@ -147,8 +194,10 @@ fn spawn_level(mut cmds: Commands, level_data: LevelData, assets: Res<GameAssets
                         pos,
                         tile.entity_kind().unwrap(),
                         HistoryBundle::<TilePos>::default(),
+                        BlockId(blocks),
                     ));
                 });
+                blocks += 1;
             }
         }
     }
@@ -170,6 +219,7 @@ fn spawn_level(mut cmds: Commands, level_data: LevelData, assets: Res<GameAssets
                 ..default()
             },
             Name::new(format!("Level {}", level_data.current_level())),
+            GroundTiles,
         ))
         .id();
 
@@ -325,7 +375,45 @@ fn reload_on_change(
     }
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, Reflect)]
+#[derive(Component)]
+struct LoadErrorText;
+
+/// Keeps the previous levels around but tells the designer why their edit didn't apply.
+fn report_load_errors<A: Asset>(
+    mut cmds: Commands,
+    mut failed_events: EventReader<AssetLoadFailedEvent<A>>,
+    error_text: Query<Entity, With<LoadErrorText>>,
+    game_state: Res<State<GameState>>,
+) {
+    for ev in failed_events.read() {
+        bevy::log::error!("{}", ev.error);
+
+        error_text
+            .iter()
+            .for_each(|e| cmds.entity(e).despawn_recursive());
+        cmds.spawn((
+            TextBundle::from_section(
+                ev.error.to_string(),
+                TextStyle {
+                    font_size: 20.,
+                    color: Color::srgb(1., 0.3, 0.3),
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.),
+                left: Val::Px(10.),
+                ..default()
+            }),
+            DependOnState::single(**game_state),
+            LoadErrorText,
+            Name::new("Level Load Error"),
+        ));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Reflect)]
 pub enum TileKind {
     Wall,
     Floor,
@@ -363,44 +451,360 @@ impl TileKind {
             TileKind::Pit => Some(EntityKind::Pit),
         }
     }
-}
 
-impl From<u8> for TileKind {
-    fn from(value: u8) -> Self {
+    pub fn from_glyph(glyph: u8) -> Option<TileKind> {
         use TileKind::*;
-        match value {
-            b'#' => Wall,
-            b'_' => Floor,
-            b'@' => Player,
-            b'b' => Pushable,
-            b'p' => Pullable,
-            b'-' => Platform,
-            b'O' => Pit,
-            _ => {
-                bevy::log::warn!("Couldnt parse tile kind defaulting to wall tile");
-                Wall
-            }
+        match glyph {
+            b'#' => Some(Wall),
+            b'_' => Some(Floor),
+            b'@' => Some(Player),
+            b'b' => Some(Pushable),
+            b'p' => Some(Pullable),
+            b'-' => Some(Platform),
+            b'O' => Some(Pit),
+            _ => None,
+        }
+    }
+
+    pub fn glyph(&self) -> char {
+        match self {
+            TileKind::Wall => '#',
+            TileKind::Floor => '_',
+            TileKind::Player => '@',
+            TileKind::Pushable => 'b',
+            TileKind::Pullable => 'p',
+            TileKind::Platform => '-',
+            TileKind::Pit => 'O',
+        }
+    }
+}
+
+/// Identifies a level independent of its position in the levels file.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect,
+)]
+#[serde(from = "RawLevelId", into = "String")]
+pub struct LevelId(pub String);
+
+impl LevelId {
+    /// Id of a level that doesn't set one in the levels file.
+    pub fn from_index(index: usize) -> Self {
+        LevelId(format!("#{}", index + 1))
+    }
+}
+
+impl std::fmt::Display for LevelId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<LevelId> for String {
+    fn from(id: LevelId) -> Self {
+        id.0
+    }
+}
+
+/// Files written before levels had ids refer to levels by their index.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawLevelId {
+    Id(String),
+    Index(usize),
+}
+
+impl From<RawLevelId> for LevelId {
+    fn from(raw: RawLevelId) -> Self {
+        match raw {
+            RawLevelId::Id(id) => LevelId(id),
+            RawLevelId::Index(index) => LevelId::from_index(index),
+        }
+    }
+}
+
+/// Information about a level that isn't needed to play it.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Reflect)]
+#[serde(default)]
+pub struct LevelMeta {
+    /// Stays the same when levels are inserted or reordered, defaults to the position
+    /// of the level in the file, see [`LevelId::from_index`].
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub hint: Option<String>,
+    pub author: Option<String>,
+    pub par_moves: Option<usize>,
+    /// Rules the level plays with, e.g. "corner", "row" or "red-herring".
+    pub tags: Vec<String>,
+}
+
+impl LevelMeta {
+    pub fn is_empty(&self) -> bool {
+        *self == LevelMeta::default()
+    }
+}
+
+/// A level as written in a levels file, either only the layout
+/// `("...")` or the layout with metadata `(layout: "...", name: "...")`.
+#[derive(Deserialize, Debug, Reflect)]
+#[serde(from = "RawStringLevel")]
+pub struct StringLevel {
+    pub layout: String,
+    pub meta: LevelMeta,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawStringLevel {
+    Plain((String,)),
+    Detailed {
+        layout: String,
+        #[serde(flatten)]
+        meta: LevelMeta,
+    },
+}
+
+impl From<RawStringLevel> for StringLevel {
+    fn from(raw: RawStringLevel) -> Self {
+        match raw {
+            RawStringLevel::Plain((layout,)) => StringLevel {
+                layout,
+                meta: LevelMeta::default(),
+            },
+            RawStringLevel::Detailed { layout, meta } => StringLevel { layout, meta },
         }
     }
 }
 
-#[derive(Deserialize, Debug, Reflect, Deref)]
-pub struct StringLevel(pub String);
 #[derive(Deserialize, Debug, Deref)]
 pub struct StringLevels(pub Vec<StringLevel>);
 
+impl StringLevel {
+    /// Rows of the level from top to bottom with all indentation removed.
+    pub fn rows(&self) -> impl Iterator<Item = String> + '_ {
+        self.layout.split('\n').map(|row| row.replace(' ', ""))
+    }
+
+    pub fn id(&self, index: usize) -> LevelId {
+        self.meta
+            .id
+            .clone()
+            .map(LevelId)
+            .unwrap_or_else(|| LevelId::from_index(index))
+    }
+
+    /// Parses the level, `index` is only used for error reporting.
+    pub fn to_level(&self, index: usize) -> Result<Level, LevelLoaderError> {
+        let rows = self.rows().collect::<Vec<String>>();
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        if width == 0 {
+            return Err(LevelLoaderError::EmptyLevel { level: index });
+        }
+
+        let mut tile_rows = Vec::new();
+        let mut player = None;
+        for (row_idx, row) in rows.iter().enumerate() {
+            let row_width = row.chars().count();
+            if row_width != width {
+                return Err(LevelLoaderError::RaggedRow {
+                    level: index,
+                    row: row_idx + 1,
+                    width: row_width,
+                    expected: width,
+                });
+            }
+            let mut tiles = Vec::new();
+            for (column_idx, glyph) in row.chars().enumerate() {
+                let (row, column) = (row_idx + 1, column_idx + 1);
+                let tile = u8::try_from(glyph)
+                    .ok()
+                    .and_then(TileKind::from_glyph)
+                    .ok_or(LevelLoaderError::UnknownGlyph {
+                        level: index,
+                        row,
+                        column,
+                        glyph,
+                    })?;
+                if matches!(tile, TileKind::Player) {
+                    if let Some(first) = player {
+                        return Err(LevelLoaderError::DuplicatePlayer {
+                            level: index,
+                            first,
+                            second: (row, column),
+                        });
+                    }
+                    player = Some((row, column));
+                }
+                tiles.push(tile);
+            }
+            tile_rows.push(tiles);
+        }
+        if player.is_none() {
+            return Err(LevelLoaderError::MissingPlayer { level: index });
+        }
+
+        // Tiles are stored bottom to top
+        Ok(Level {
+            id: self.id(index),
+            tiles: tile_rows.into_iter().rev().flatten().collect(),
+            size: UVec2::new(width as u32, rows.len() as u32),
+            meta: self.meta.clone(),
+        })
+    }
+}
+
+impl StringLevels {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LevelLoaderError> {
+        Ok(ron::de::from_bytes::<StringLevels>(bytes)?)
+    }
+
+    pub fn to_levels(&self) -> Result<Levels, LevelLoaderError> {
+        let levels = self
+            .iter()
+            .enumerate()
+            .map(|(idx, string_level)| string_level.to_level(idx))
+            .collect::<Result<Vec<Level>, LevelLoaderError>>()?;
+        let levels = Levels(levels);
+        levels.check_ids()?;
+
+        Ok(levels)
+    }
+}
+
 #[derive(TypePath, Debug, Deserialize, Deref, DerefMut, Asset)]
 pub struct Levels(pub Vec<Level>);
 
-#[derive(Deserialize, Debug, Reflect)]
+impl Levels {
+    pub fn index_of(&self, id: &LevelId) -> Option<usize> {
+        self.iter().position(|level| level.id == *id)
+    }
+
+    pub fn get_by_id(&self, id: &LevelId) -> Option<&Level> {
+        self.iter().find(|level| level.id == *id)
+    }
+
+    pub fn get_by_id_mut(&mut self, id: &LevelId) -> Option<&mut Level> {
+        self.iter_mut().find(|level| level.id == *id)
+    }
+
+    pub fn check_ids(&self) -> Result<(), LevelLoaderError> {
+        for (second, level) in self.iter().enumerate() {
+            if let Some(first) = self.index_of(&level.id).filter(|first| *first != second) {
+                return Err(LevelLoaderError::DuplicateId {
+                    id: level.id.clone(),
+                    first,
+                    second,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes the levels in the format read by [`LevelLoader`].
+    /// Comments of the original file are not preserved.
+    pub fn to_ron(&self) -> String {
+        let mut contents = String::from("([\n");
+        for level in self.iter() {
+            if level.meta.is_empty() {
+                contents.push_str("(\n   \"");
+                contents.push_str(&level.to_rows().join("\n    "));
+                contents.push_str("\"\n),\n");
+                continue;
+            }
+
+            contents.push_str("(\n    layout: \"");
+            contents.push_str(&level.to_rows().join("\n             "));
+            contents.push_str("\",\n");
+            let meta = &level.meta;
+            let mut field = |name: &str, value: Option<String>| {
+                if let Some(value) = value {
+                    contents.push_str(&format!("    {}: {},\n", name, value));
+                }
+            };
+            let string = |value: &String| ron::to_string(value).expect("Strings are valid ron");
+            field("id", meta.id.as_ref().map(string));
+            field("name", meta.name.as_ref().map(string));
+            field("hint", meta.hint.as_ref().map(string));
+            field("author", meta.author.as_ref().map(string));
+            field("par_moves", meta.par_moves.map(|par| par.to_string()));
+            field(
+                "tags",
+                (!meta.tags.is_empty())
+                    .then(|| ron::to_string(&meta.tags).expect("Strings are valid ron")),
+            );
+            contents.push_str("),\n");
+        }
+        contents.push_str("])\n");
+        contents
+    }
+}
+
+/// Whether a levels file has comments, which [`Levels::to_ron`] drops.
+pub fn has_comments(ron: &str) -> bool {
+    let mut chars = ron.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            '/' if !in_string && matches!(chars.peek(), Some('/' | '*')) => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+#[derive(Deserialize, Debug, Default, Reflect)]
 pub struct Level {
+    #[serde(default)]
+    pub id: LevelId,
     pub tiles: Vec<TileKind>,
     pub size: UVec2,
+    #[serde(default)]
+    pub meta: LevelMeta,
+}
+
+impl Level {
+    /// Hash of the layout that stays stable across builds, used to recognize a level
+    /// after levels were reordered.
+    pub fn fingerprint(&self) -> u64 {
+        // FNV-1a
+        self.to_rows()
+            .iter()
+            .flat_map(|row| row.bytes().chain(Some(b'\n')))
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+
+    /// Rows of glyphs from top to bottom.
+    pub fn to_rows(&self) -> Vec<String> {
+        self.tiles
+            .chunks_exact(self.size.x as usize)
+            .rev()
+            .map(|row| row.iter().map(TileKind::glyph).collect())
+            .collect()
+    }
 }
 
 #[derive(Default)]
 pub struct LevelLoader;
 
+impl LevelLoader {
+    /// Whether the file at `path` is read by this loader, only those can be saved again.
+    pub fn handles(path: &Path) -> bool {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            return false;
+        };
+        LevelLoader
+            .extensions()
+            .iter()
+            .any(|extension| name.ends_with(&format!(".{}", extension)))
+    }
+}
+
+/// Rows and columns are counted from the top left of a level, starting at 1.
 #[non_exhaustive]
 #[derive(Debug, Error)]
 pub enum LevelLoaderError {
@@ -408,6 +812,43 @@ pub enum LevelLoaderError {
     Io(#[from] std::io::Error),
     #[error("Could not parse the ron: {0}")]
     RonError(#[from] ron::error::SpannedError),
+    #[error("File is not valid UTF-8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("Level {}: row {row} is {width} tiles wide, expected {expected}", .level + 1)]
+    RaggedRow {
+        level: usize,
+        row: usize,
+        width: usize,
+        expected: usize,
+    },
+    #[error("Level {}: unknown glyph {glyph:?} at row {row}, column {column}", .level + 1)]
+    UnknownGlyph {
+        level: usize,
+        row: usize,
+        column: usize,
+        glyph: char,
+    },
+    #[error("Level {}: no player", .level + 1)]
+    MissingPlayer { level: usize },
+    #[error(
+        "Level {}: second player at row {}, column {}",
+        .level + 1,
+        .second.0,
+        .second.1
+    )]
+    DuplicatePlayer {
+        level: usize,
+        first: (usize, usize),
+        second: (usize, usize),
+    },
+    #[error("Level {}: level is empty", .level + 1)]
+    EmptyLevel { level: usize },
+    #[error("Level {}: id \"{id}\" is already used by level {}", .second + 1, .first + 1)]
+    DuplicateId {
+        id: LevelId,
+        first: usize,
+        second: usize,
+    },
 }
 
 impl AssetLoader for LevelLoader {
@@ -426,31 +867,7 @@ impl AssetLoader for LevelLoader {
         Box::pin(async move {
             let mut bytes = Vec::new();
             reader.read_to_end(&mut bytes).await?;
-            let string_levels = ron::de::from_bytes::<StringLevels>(&bytes)?;
-            let levels = string_levels
-                .iter()
-                .map(|string_level| {
-                    let height = string_level.split('\n').count() as u32;
-                    let tiles = string_level
-                        .replace(['\n', ' '], "")
-                        .as_bytes()
-                        .iter()
-                        .map(|byte| TileKind::from(*byte))
-                        .collect::<Vec<TileKind>>();
-                    let width = tiles.len() as u32 / height;
-                    let tiles = tiles
-                        .chunks_exact(width as usize)
-                        .rev()
-                        .flat_map(|chunk| chunk.to_vec())
-                        .collect::<Vec<TileKind>>();
-                    Level {
-                        tiles,
-                        size: UVec2::new(width, height),
-                    }
-                })
-                .collect::<Vec<Level>>();
-
-            Ok(Levels(levels))
+            StringLevels::from_bytes(&bytes)?.to_levels()
         })
     }
 
@@ -458,3 +875,111 @@ impl AssetLoader for LevelLoader {
         &["levels.ron"]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_comments_outside_of_strings() {
+        assert!(has_comments("([\n    // First level\n    (\"#@#\"),\n])"));
+        assert!(has_comments("([ /* hard */ (\"#@#\") ])"));
+        assert!(!has_comments(
+            "([(layout: \"#@#\", name: \"http://a \\\" //b\")])"
+        ));
+    }
+
+    #[test]
+    fn only_ron_levels_are_saved() {
+        assert!(LevelLoader::handles(Path::new("levels/main.levels.ron")));
+        assert!(!LevelLoader::handles(Path::new("levels/classic.xsb")));
+        assert!(!LevelLoader::handles(Path::new("levels/index.packs.ron")));
+    }
+
+    const PACK: &str = r#####"([
+        (
+            "###
+             #@#
+             ###"
+        ),
+        (
+            layout: "####
+                     #@b#
+                     ####",
+            id: "first-push",
+            name: "First push",
+            par_moves: 1,
+            tags: ["corner"],
+        ),
+    ])"#####;
+
+    #[test]
+    fn reads_plain_and_detailed_levels() {
+        let levels = StringLevels::from_bytes(PACK.as_bytes()).unwrap();
+        assert_eq!(levels.len(), 2);
+
+        assert_eq!(levels[0].rows().collect::<Vec<_>>(), ["###", "#@#", "###"]);
+        assert!(levels[0].meta.is_empty());
+
+        assert_eq!(
+            levels[1].rows().collect::<Vec<_>>(),
+            ["####", "#@b#", "####"]
+        );
+        assert_eq!(
+            levels[1].meta,
+            LevelMeta {
+                id: Some("first-push".into()),
+                name: Some("First push".into()),
+                par_moves: Some(1),
+                tags: vec!["corner".into()],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn level_ids_are_read_from_ids_or_indices() {
+        assert_eq!(
+            ron::from_str::<LevelId>(r#""first-push""#).unwrap(),
+            LevelId("first-push".into())
+        );
+        assert_eq!(
+            ron::from_str::<LevelId>("2").unwrap(),
+            LevelId::from_index(2)
+        );
+        assert_eq!(LevelId::from_index(2), LevelId("#3".into()));
+        assert_eq!(
+            ron::to_string(&LevelId::from_index(2)).unwrap(),
+            r##""#3""##
+        );
+
+        let levels = StringLevels::from_bytes(PACK.as_bytes())
+            .unwrap()
+            .to_levels()
+            .unwrap();
+        assert_eq!(levels[0].id, LevelId::from_index(0));
+        assert_eq!(levels[1].id, LevelId("first-push".into()));
+    }
+
+    #[test]
+    fn to_ron_round_trips() {
+        let levels = StringLevels::from_bytes(PACK.as_bytes())
+            .unwrap()
+            .to_levels()
+            .unwrap();
+        let ron = levels.to_ron();
+        let reread = StringLevels::from_bytes(ron.as_bytes())
+            .unwrap()
+            .to_levels()
+            .unwrap();
+
+        assert_eq!(reread.len(), levels.len());
+        for (level, reread) in levels.iter().zip(reread.iter()) {
+            assert_eq!(reread.id, level.id);
+            assert_eq!(reread.size, level.size);
+            assert_eq!(reread.to_rows(), level.to_rows());
+            assert_eq!(reread.meta, level.meta);
+        }
+        assert_eq!(reread.to_ron(), ron);
+    }
+}
@@ -1,4 +1,6 @@
-use bevy::prelude::*;
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::{log, prelude::*};
 
 use crate::{
     cleanup::DependOnState,
@@ -6,7 +8,11 @@ use crate::{
     ui::NineSliceButtonText,
 };
 
-use super::level::LevelData;
+use super::{
+    level::{LevelData, LevelId, Levels},
+    pack::{CurrentPack, LevelPacks},
+    save::SaveData,
+};
 
 pub struct LevelSelectPlugin;
 
@@ -14,24 +20,98 @@ impl Plugin for LevelSelectPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<CurrentLevel>()
             .register_type::<CurrentLevel>()
+            .add_systems(OnEnter(GameState::LevelTransition), resolve_current_level)
+            .add_systems(OnEnter(GameState::PackSelect), spawn_pack_select)
             .add_systems(OnEnter(GameState::LevelSelect), spawn_level_select)
             .add_systems(
                 Update,
-                handle_buttons.run_if(in_state(GameState::LevelSelect)),
+                (
+                    (handle_pack_buttons, handle_settings_button)
+                        .run_if(in_state(GameState::PackSelect)),
+                    (handle_buttons, handle_back_button).run_if(in_state(GameState::LevelSelect)),
+                ),
             );
     }
 }
 
+/// Level that is played, read it through [`LevelData`] which resolves the id.
 #[derive(Resource, Deref, DerefMut, Reflect, Default, Debug)]
 #[reflect(Resource)]
-pub struct CurrentLevel(pub usize);
+pub struct CurrentLevel(pub LevelId);
 
-#[derive(Component, Deref, DerefMut, Clone)]
-struct LevelButton(pub usize);
+#[derive(Component, Clone)]
+struct LevelButton {
+    id: LevelId,
+    index: usize,
+}
 
 impl From<LevelButton> for String {
     fn from(value: LevelButton) -> Self {
-        format!("{}", *value + 1)
+        format!("{}", value.index + 1)
+    }
+}
+
+#[derive(Component, Clone)]
+struct PackButton {
+    id: String,
+    label: String,
+}
+
+impl From<PackButton> for String {
+    fn from(value: PackButton) -> Self {
+        value.label
+    }
+}
+
+/// Returns to the pack picker.
+#[derive(Component, Clone)]
+struct BackButton;
+
+impl From<BackButton> for String {
+    fn from(_: BackButton) -> Self {
+        "Packs".to_string()
+    }
+}
+
+/// Opens the settings.
+#[derive(Component, Clone)]
+struct SettingsButton;
+
+impl From<SettingsButton> for String {
+    fn from(_: SettingsButton) -> Self {
+        "Settings".to_string()
+    }
+}
+
+/// Levels unlock once the level before them in the same pack is completed.
+pub fn is_unlocked(save: &SaveData, pack: &str, levels: &Levels, index: usize) -> bool {
+    let completed = |index: usize| {
+        levels
+            .get(index)
+            .is_some_and(|level| save.is_completed(pack, &level.id))
+    };
+    index == 0 || completed(index) || completed(index - 1)
+}
+
+/// Makes sure [`CurrentLevel`] names an existing level before it is played or edited.
+fn resolve_current_level(
+    mut current_level: ResMut<CurrentLevel>,
+    current_pack: Res<CurrentPack>,
+    levels: Res<Assets<Levels>>,
+) {
+    let levels = levels
+        .get(&current_pack.levels)
+        .expect("Level assets should be loaded");
+    if levels.index_of(&current_level).is_some() {
+        return;
+    }
+    if let Some(first) = levels.first() {
+        log::info!(
+            "Level {} doesn't exist, starting at {}",
+            **current_level,
+            first.id
+        );
+        current_level.0 = first.id.clone();
     }
 }
 
@@ -39,20 +119,181 @@ fn handle_buttons(
     mut game_state: ResMut<NextState<GameState>>,
     buttons: Query<(&LevelButton, &Interaction), Changed<Interaction>>,
     mut current_level: ResMut<CurrentLevel>,
+    current_pack: Res<CurrentPack>,
+    levels: Res<Assets<Levels>>,
+    save: Res<SaveData>,
 ) {
+    let levels = levels
+        .get(&current_pack.levels)
+        .expect("Level assets should be loaded");
     buttons
         .iter()
         .for_each(|(level, interaction)| match interaction {
-            Interaction::Pressed => {
-                current_level.0 = **level;
+            Interaction::Pressed if is_unlocked(&save, &current_pack.id, levels, level.index) => {
+                current_level.0 = level.id.clone();
                 game_state.set(GameState::LevelTransition);
             }
-            Interaction::Hovered => {}
-            Interaction::None => {}
+            _ => {}
+        });
+}
+
+fn handle_back_button(
+    mut game_state: ResMut<NextState<GameState>>,
+    buttons: Query<&Interaction, (With<BackButton>, Changed<Interaction>)>,
+) {
+    if buttons
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        game_state.set(GameState::PackSelect);
+    }
+}
+
+fn handle_settings_button(
+    mut game_state: ResMut<NextState<GameState>>,
+    buttons: Query<&Interaction, (With<SettingsButton>, Changed<Interaction>)>,
+) {
+    if buttons
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        game_state.set(GameState::Settings);
+    }
+}
+
+fn handle_pack_buttons(
+    mut game_state: ResMut<NextState<GameState>>,
+    buttons: Query<(&PackButton, &Interaction), Changed<Interaction>>,
+    mut current_pack: ResMut<CurrentPack>,
+    mut current_level: ResMut<CurrentLevel>,
+    packs: Res<Assets<LevelPacks>>,
+    assets: Res<GameAssets>,
+    save: Res<SaveData>,
+) {
+    let packs = packs
+        .get(&assets.packs)
+        .expect("Pack index should be loaded");
+    for (button, interaction) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(pack) = packs.get_by_id(&button.id) else {
+            continue;
+        };
+        *current_pack = pack.into();
+        // Resolved to the first level of the pack if there is no progress yet
+        current_level.0 = save
+            .pack(&pack.id)
+            .and_then(|progress| progress.last_played.clone())
+            .unwrap_or_default();
+        game_state.set(GameState::LevelSelect);
+    }
+}
+
+fn spawn_pack_select(
+    mut cmds: Commands,
+    packs: Res<Assets<LevelPacks>>,
+    levels: Res<Assets<Levels>>,
+    assets: Res<GameAssets>,
+    save: Res<SaveData>,
+) {
+    let button_style = Style {
+        width: Val::Px(300.0),
+        height: Val::Px(60.0),
+        margin: UiRect::all(Val::Px(10.)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        border: UiRect::all(Val::Px(2.)),
+        ..default()
+    };
+    let column = cmds
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    align_content: AlignContent::Center,
+                    margin: UiRect::all(Val::Auto),
+                    ..default()
+                },
+                ..default()
+            },
+            DependOnState::single(GameState::PackSelect),
+            Name::new("Pack Select"),
+        ))
+        .id();
+
+    let packs = packs
+        .get(&assets.packs)
+        .expect("Pack index should be loaded");
+    for pack in packs.iter() {
+        let Some(pack_levels) = levels.get(&pack.levels) else {
+            continue;
+        };
+        let completed = pack_levels
+            .iter()
+            .filter(|level| save.is_completed(&pack.id, &level.id))
+            .count();
+        cmds.add(NineSliceButtonText {
+            button: PackButton {
+                id: pack.id.clone(),
+                label: format!("{} ({}/{})", pack.name, completed, pack_levels.len()),
+            },
+            style: button_style.clone(),
+            texture: assets.button.clone_weak(),
+            parent: column,
+            disabled: false,
         });
+    }
+    cmds.add(NineSliceButtonText {
+        button: SettingsButton,
+        style: button_style,
+        texture: assets.button.clone_weak(),
+        parent: column,
+        disabled: false,
+    });
+}
+
+fn spawn_checkmark(cmds: &mut Commands, parent: Entity) {
+    let stroke = |left: f32, top: f32, width: f32, angle: f32| NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(left),
+            top: Val::Px(top),
+            width: Val::Px(width),
+            height: Val::Px(3.),
+            ..default()
+        },
+        background_color: Color::srgb(0.4, 0.8, 0.4).into(),
+        transform: Transform::from_rotation(Quat::from_rotation_z(angle)),
+        ..default()
+    };
+    cmds.entity(parent).with_children(|parent| {
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(14.),
+                    right: Val::Px(14.),
+                    width: Val::Px(20.),
+                    height: Val::Px(20.),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn(stroke(0., 11., 8., FRAC_PI_4));
+                parent.spawn(stroke(4., 8., 15., -FRAC_PI_4));
+            });
+    });
 }
 
-fn spawn_level_select(mut cmds: Commands, level_data: LevelData, assets: Res<GameAssets>) {
+fn spawn_level_select(
+    mut cmds: Commands,
+    level_data: LevelData,
+    assets: Res<GameAssets>,
+    save: Res<SaveData>,
+) {
     let button_texture = assets.button.clone_weak();
     let button_style = Style {
         width: Val::Px(75.0),
@@ -63,6 +304,8 @@ fn spawn_level_select(mut cmds: Commands, level_data: LevelData, assets: Res<Gam
         border: UiRect::all(Val::Px(2.)),
         ..default()
     };
+    let pack = &level_data.current_pack().id;
+    let levels = level_data.levels();
     let amount_levels = level_data.amount_levels();
     let cols = 5;
     let rows = (amount_levels / cols) + 1;
@@ -86,15 +329,36 @@ fn spawn_level_select(mut cmds: Commands, level_data: LevelData, assets: Res<Gam
             if idx >= amount_levels {
                 break;
             }
+            // Wraps the button so the checkmark can be placed on top of it
+            let cell = cmds.spawn(NodeBundle::default()).id();
+            cmds.entity(row_node).add_child(cell);
+            let id = levels[idx].id.clone();
+            let completed = save.is_completed(pack, &id);
             cmds.add(NineSliceButtonText {
-                button: LevelButton(idx),
+                button: LevelButton { id, index: idx },
                 style: button_style.clone(),
                 texture: button_texture.clone_weak(),
-                parent: row_node,
+                parent: cell,
+                disabled: !is_unlocked(&save, pack, levels, idx),
             });
+            if completed {
+                spawn_checkmark(&mut cmds, cell);
+            }
         }
         row_nodes.push(row_node);
     }
+    let back_row = cmds.spawn(NodeBundle::default()).id();
+    cmds.add(NineSliceButtonText {
+        button: BackButton,
+        style: Style {
+            width: Val::Px(200.0),
+            ..button_style.clone()
+        },
+        texture: button_texture.clone_weak(),
+        parent: back_row,
+        disabled: false,
+    });
+    row_nodes.push(back_row);
     cmds.spawn((
         NodeBundle {
             style: Style {
@@ -7,7 +7,7 @@ use crate::{
     event_scheduler::{EventScheduler, EventSchedulerPlugin},
 };
 
-use super::{level_select::CurrentLevel, GameState};
+use super::{level::LevelData, GameState};
 
 const CURRENT_STATE: GameState = GameState::LevelTransition;
 
@@ -31,9 +31,15 @@ enum LevelTransitionEvent {
 
 fn spawn_level_card(
     mut cmds: Commands,
-    current_level: Res<CurrentLevel>,
+    level_data: LevelData,
     mut level_transition_scheduler: ResMut<EventScheduler<LevelTransitionEvent>>,
 ) {
+    let meta = &level_data.current_level_data().meta;
+    let title = meta
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("Level {}", level_data.current_index() + 1));
+
     cmds.spawn((
         NodeBundle {
             style: Style {
@@ -66,7 +72,7 @@ fn spawn_level_card(
                 ..default()
             },
             text: Text::from_section(
-                format!("Level {}", **current_level),
+                title,
                 TextStyle {
                     font_size: 48.,
                     color: Color::BLACK,
@@ -75,6 +81,16 @@ fn spawn_level_card(
             ),
             ..default()
         });
+        if let Some(hint) = &meta.hint {
+            parent.spawn(TextBundle::from_section(
+                hint.clone(),
+                TextStyle {
+                    font_size: 24.,
+                    color: Color::BLACK,
+                    ..default()
+                },
+            ));
+        }
     })
     .with_children(|parent| {
         parent.spawn(TextBundle {
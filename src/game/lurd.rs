@@ -0,0 +1,395 @@
+//! Solutions in the LURD notation used by the Sokoban community.
+//!
+//! Every move is one of `l`, `u`, `r`, `d`, uppercase when a block was pushed.
+//! Pulling a [`Pullable`](super::EntityKind::Pullable) block isn't part of
+//! the original notation, those moves get a `~` suffix.
+
+use std::time::Duration;
+
+use bevy::{log, prelude::*};
+use leafwing_input_manager::prelude::*;
+use thiserror::Error;
+
+use super::{
+    history::{HandleHistoryEvents, HistoryEvent},
+    level::LevelData,
+    player::{player_movement, PlayerMoved},
+    replay::{LoggedStep, MoveLog, PendingReplay, Step},
+    Dir, GameAction, GameState,
+};
+
+/// Longest solution that is imported, far longer than any real solution.
+pub const MAX_MOVES: usize = 100_000;
+
+/// Time between two moves when playing back an imported solution.
+const IMPORT_STEP: Duration = Duration::from_millis(150);
+
+pub struct LurdPlugin;
+
+impl Plugin for LurdPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Solution>()
+            .register_type::<Solution>()
+            .add_systems(
+                OnTransition {
+                    exited: GameState::LevelTransition,
+                    entered: GameState::Play,
+                },
+                reset_solution,
+            )
+            .add_systems(
+                Update,
+                (
+                    track_solution
+                        .after(player_movement)
+                        .before(HandleHistoryEvents)
+                        .run_if(in_state(GameState::Play)),
+                    copy_solution
+                        .after(track_solution)
+                        .run_if(in_state(GameState::Play).or_else(in_state(GameState::Won))),
+                    paste_solution.run_if(in_state(GameState::Play)),
+                ),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub struct LurdMove {
+    pub dir: Dir,
+    pub pushed: bool,
+    pub pulled: bool,
+}
+
+impl From<PlayerMoved> for LurdMove {
+    fn from(moved: PlayerMoved) -> Self {
+        LurdMove {
+            dir: moved.direction,
+            pushed: moved.pushed,
+            pulled: moved.pulled,
+        }
+    }
+}
+
+fn dir_char(dir: Dir) -> char {
+    match dir {
+        Dir::Left => 'l',
+        Dir::Up => 'u',
+        Dir::Right => 'r',
+        Dir::Down => 'd',
+    }
+}
+
+fn char_dir(c: char) -> Option<Dir> {
+    match c.to_ascii_lowercase() {
+        'l' => Some(Dir::Left),
+        'u' => Some(Dir::Up),
+        'r' => Some(Dir::Right),
+        'd' => Some(Dir::Down),
+        _ => None,
+    }
+}
+
+pub fn to_lurd(moves: &[LurdMove]) -> String {
+    let mut lurd = String::with_capacity(moves.len());
+    for mv in moves {
+        let c = dir_char(mv.dir);
+        lurd.push(if mv.pushed { c.to_ascii_uppercase() } else { c });
+        if mv.pulled {
+            lurd.push('~');
+        }
+    }
+    lurd
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LurdError {
+    #[error("Unexpected {found:?} at position {position}")]
+    Unexpected { found: char, position: usize },
+    #[error("Count at position {position} isn't followed by a move")]
+    DanglingCount { position: usize },
+    #[error("Solution is longer than {MAX_MOVES} moves at position {position}")]
+    TooLong { position: usize },
+}
+
+/// Parses a LURD string, whitespace is ignored and moves may be prefixed with a repeat count.
+pub fn parse_lurd(lurd: &str) -> Result<Vec<LurdMove>, LurdError> {
+    let mut moves: Vec<LurdMove> = Vec::new();
+    let mut count: Option<(usize, usize)> = None;
+    for (position, c) in lurd.chars().enumerate() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if let Some(digit) = c.to_digit(10) {
+            let (start, n) = count.unwrap_or((position, 0));
+            count = Some((start, n.saturating_mul(10).saturating_add(digit as usize)));
+            continue;
+        }
+        if c == '~' && count.is_none() {
+            let Some(last) = moves.last_mut().filter(|last| !last.pulled) else {
+                return Err(LurdError::Unexpected { found: c, position });
+            };
+            last.pulled = true;
+            continue;
+        }
+        let Some(dir) = char_dir(c) else {
+            return Err(LurdError::Unexpected { found: c, position });
+        };
+        let mv = LurdMove {
+            dir,
+            pushed: c.is_ascii_uppercase(),
+            pulled: false,
+        };
+        let (start, n) = count.take().unwrap_or((position, 1));
+        if n > MAX_MOVES - moves.len() {
+            return Err(LurdError::TooLong { position: start });
+        }
+        moves.extend(std::iter::repeat(mv).take(n));
+    }
+    if let Some((position, _)) = count {
+        return Err(LurdError::DanglingCount { position });
+    }
+    Ok(moves)
+}
+
+/// Moves that lead to the current state of the level, kept in sync with undo, redo and reset.
+#[derive(Resource, Reflect, Default, Debug)]
+#[reflect(Resource)]
+pub struct Solution {
+    pub moves: Vec<LurdMove>,
+    /// History steps in the order they happened, the last one is undone first.
+    undo: Vec<SolutionStep>,
+    /// Undone history steps, the last one is redone first.
+    redo: Vec<SolutionStep>,
+}
+
+#[derive(Reflect, Debug)]
+enum SolutionStep {
+    Move(LurdMove),
+    /// Moves before the reset.
+    Reset(Vec<LurdMove>),
+}
+
+impl Solution {
+    fn apply(&mut self, step: SolutionStep) {
+        let step = match step {
+            SolutionStep::Move(mv) => {
+                self.moves.push(mv);
+                SolutionStep::Move(mv)
+            }
+            SolutionStep::Reset(_) => SolutionStep::Reset(std::mem::take(&mut self.moves)),
+        };
+        self.undo.push(step);
+    }
+
+    fn undo(&mut self) {
+        let Some(step) = self.undo.pop() else {
+            return;
+        };
+        match &step {
+            SolutionStep::Move(_) => {
+                self.moves.pop();
+            }
+            SolutionStep::Reset(moves) => self.moves.clone_from(moves),
+        }
+        self.redo.push(step);
+    }
+
+    fn redo(&mut self) {
+        if let Some(step) = self.redo.pop() {
+            self.apply(step);
+        }
+    }
+}
+
+fn reset_solution(mut solution: ResMut<Solution>) {
+    *solution = Solution::default();
+}
+
+fn track_solution(
+    mut solution: ResMut<Solution>,
+    mut history_events: EventReader<HistoryEvent>,
+    mut player_moved: EventReader<PlayerMoved>,
+) {
+    // Every record comes with the move that caused it
+    let mut moved = player_moved.read();
+    for ev in history_events.read() {
+        match ev {
+            HistoryEvent::Record => {
+                let Some(mv) = moved.next() else {
+                    continue;
+                };
+                solution.redo.clear();
+                solution.apply(SolutionStep::Move((*mv).into()));
+            }
+            HistoryEvent::Rewind => solution.undo(),
+            HistoryEvent::Redo => solution.redo(),
+            HistoryEvent::Reset => {
+                solution.redo.clear();
+                solution.apply(SolutionStep::Reset(Vec::new()));
+            }
+        }
+    }
+}
+
+fn copy_solution(solution: Res<Solution>, actions: Query<&ActionState<GameAction>>) {
+    let Ok(actions) = actions.get_single() else {
+        return;
+    };
+    if !actions.just_pressed(&GameAction::CopySolution) {
+        return;
+    }
+    let lurd = to_lurd(&solution.moves);
+    log::info!("Solution: {}", lurd);
+    if let Err(err) = clipboard::set(&lurd) {
+        log::error!("Could not copy solution to the clipboard: {}", err);
+    }
+}
+
+/// Restarts the current level and plays back the LURD string from the clipboard.
+fn paste_solution(
+    mut cmds: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    actions: Query<&ActionState<GameAction>>,
+    level_data: LevelData,
+) {
+    let Ok(actions) = actions.get_single() else {
+        return;
+    };
+    if !actions.just_pressed(&GameAction::PasteSolution) {
+        return;
+    }
+    let lurd = match clipboard::get() {
+        Ok(lurd) => lurd,
+        Err(err) => {
+            log::error!("Could not read solution from the clipboard: {}", err);
+            return;
+        }
+    };
+    let moves = match parse_lurd(&lurd) {
+        Ok(moves) => moves,
+        Err(err) => {
+            log::error!("Could not parse solution: {}", err);
+            return;
+        }
+    };
+
+    // Whether a block gets pushed is up to the level, only the directions are played back
+    let steps: Vec<_> = moves
+        .iter()
+        .enumerate()
+        .map(|(i, mv)| {
+            let millis = IMPORT_STEP.as_millis() as u64 * (i as u64 + 1);
+            LoggedStep(millis, Step::Move(mv.dir))
+        })
+        .collect();
+    log::info!("Importing solution with {} moves", steps.len());
+
+    cmds.insert_resource(PendingReplay(MoveLog {
+        pack: level_data.current_pack().id.clone(),
+        level: level_data.current_level().clone(),
+        fingerprint: level_data.current_level_data().fingerprint(),
+        steps,
+    }));
+    next_state.set(GameState::LevelTransition);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod clipboard {
+    pub fn set(contents: &str) -> Result<(), arboard::Error> {
+        arboard::Clipboard::new()?.set_text(contents)
+    }
+
+    pub fn get() -> Result<String, arboard::Error> {
+        arboard::Clipboard::new()?.get_text()
+    }
+}
+
+/// The browser clipboard can only be accessed asynchronously, the solution is only logged.
+#[cfg(target_arch = "wasm32")]
+mod clipboard {
+    pub fn set(_contents: &str) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    pub fn get() -> Result<String, &'static str> {
+        Err("Pasting solutions isn't supported on the web")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mv(dir: Dir, pushed: bool, pulled: bool) -> LurdMove {
+        LurdMove {
+            dir,
+            pushed,
+            pulled,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let moves = vec![
+            mv(Dir::Left, false, false),
+            mv(Dir::Up, true, false),
+            mv(Dir::Right, false, true),
+            mv(Dir::Down, false, false),
+            mv(Dir::Down, true, false),
+        ];
+        let lurd = to_lurd(&moves);
+        assert_eq!(lurd, "lUr~dD");
+        assert_eq!(parse_lurd(&lurd), Ok(moves));
+    }
+
+    #[test]
+    fn counts_and_whitespace() {
+        assert_eq!(parse_lurd("3r 2U\nl"), parse_lurd("rrrUUl"));
+        assert_eq!(parse_lurd("12d").map(|moves| moves.len()), Ok(12));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse_lurd("lx"),
+            Err(LurdError::Unexpected {
+                found: 'x',
+                position: 1
+            })
+        );
+        assert_eq!(
+            parse_lurd("~"),
+            Err(LurdError::Unexpected {
+                found: '~',
+                position: 0
+            })
+        );
+        assert_eq!(
+            parse_lurd("l~~"),
+            Err(LurdError::Unexpected {
+                found: '~',
+                position: 2
+            })
+        );
+        assert_eq!(
+            parse_lurd("ur12"),
+            Err(LurdError::DanglingCount { position: 2 })
+        );
+    }
+
+    #[test]
+    fn too_long() {
+        assert_eq!(
+            parse_lurd("l99999999999999r"),
+            Err(LurdError::TooLong { position: 1 })
+        );
+        let limit = format!("{MAX_MOVES}u");
+        assert_eq!(parse_lurd(&limit).map(|moves| moves.len()), Ok(MAX_MOVES));
+        assert_eq!(
+            parse_lurd(&format!("{limit}d")),
+            Err(LurdError::TooLong {
+                position: limit.len()
+            })
+        );
+    }
+}
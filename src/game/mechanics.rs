@@ -1,12 +1,17 @@
-use bevy::{ecs::world::Command, log, prelude::*, utils::hashbrown::HashSet};
+use bevy::{ecs::world::Command, log, prelude::*};
 use bevy_simple_tilemap::TileMap;
 
 use super::{
     collision::CollisionMap,
-    history::{CurrentTime, HandleHistoryEvents, History, HistoryEvent, PreviousComponent},
-    level::LevelRoot,
-    player::Player,
-    util::{CARDINALS, CARDINALS_DIR},
+    history::{
+        CurrentTime, HandleHistoryEvents, History, HistoryEvent, HistoryMode, PreviousComponent,
+    },
+    level::{GroundTiles, LevelData, LevelRoot},
+    player::{MoveQueue, Player},
+    puzzle::Board,
+    replay::Replay,
+    tween::{sinking_sprite, FromPit},
+    win::{LevelCompleted, LevelStats},
     EntityKind, GameState, TilePos,
 };
 
@@ -14,15 +19,29 @@ pub struct MechanicsPlugin;
 
 impl Plugin for MechanicsPlugin {
     fn build(&self, app: &mut App) {
-        app.register_type::<HistoryStore>()
-            .register_type::<DespawnHistory>();
-        app.init_resource::<DespawnHistory>();
+        app.register_type::<BlockId>()
+            .register_type::<HistoryStore>()
+            .register_type::<DespawnHistory>()
+            .register_type::<ResetHistory>();
+        app.init_resource::<DespawnHistory>()
+            .init_resource::<ResetHistory>()
+            .add_event::<PitFilled>();
         app.add_systems(
+            OnTransition {
+                exited: GameState::LevelTransition,
+                entered: GameState::Play,
+            },
+            reset_despawn_history,
+        )
+        .add_systems(
             Update,
             (
-                despawn_on_pit,
+                despawn_on_pit.after(HandleHistoryEvents),
                 check_win,
-                (rewind, apply_deferred).chain().before(HandleHistoryEvents),
+                (rewind, apply_deferred)
+                    .chain()
+                    .before(HandleHistoryEvents)
+                    .run_if(resource_equals(HistoryMode::Components)),
             )
                 .run_if(in_state(GameState::Play)),
         );
@@ -31,27 +50,57 @@ impl Plugin for MechanicsPlugin {
 
 #[derive(Component)]
 pub struct Pit;
-fn despawn_on_pit(
+
+/// Identifies a block across despawns, blocks are numbered when the level is spawned.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlockId(pub usize);
+
+/// A block fell into the pit at the position and filled it.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PitFilled(pub TilePos);
+
+pub fn set_pit_tile(tilemap: &mut TileMap, pos: TilePos, filled: bool) {
+    tilemap.set_tile(
+        pos.extend(pos.y),
+        Some(bevy_simple_tilemap::Tile {
+            sprite_index: if filled { 16 } else { 18 },
+            ..default()
+        }),
+    );
+}
+
+pub fn despawn_on_pit(
     mut cmds: Commands,
-    q: Query<(Entity, &TilePos, &EntityKind)>,
+    q: Query<(
+        Entity,
+        &TilePos,
+        &EntityKind,
+        Option<(&Transform, &Handle<Image>)>,
+    )>,
     pit: Query<(Entity, &TilePos), With<Pit>>,
-    mut tilemap: Query<&mut TileMap>,
+    mut tilemap: Query<&mut TileMap, With<GroundTiles>>,
+    mut pit_filled: EventWriter<PitFilled>,
+    move_queue: Res<MoveQueue>,
 ) {
     let Ok(mut tilemap) = tilemap.get_single_mut() else {
         return;
     };
-    for (entity, pos, kind) in q.iter() {
-        if matches!(kind, EntityKind::Pushable) {
+    for (entity, pos, kind, sprite) in q.iter() {
+        if kind.fills_pit() {
             if let Some((pit_entity, _)) = pit.iter().find(|(_, pit_pos)| *pit_pos == pos) {
+                if let Some((transform, texture)) = sprite {
+                    // The block is gone right away, a copy of its sprite sinks in its place
+                    cmds.spawn(sinking_sprite(
+                        texture.clone_weak(),
+                        *transform,
+                        pos.wpos(),
+                        move_queue.cooldown,
+                    ));
+                }
                 cmds.add(DespawnSokobanEntityCommand(entity));
                 cmds.entity(pit_entity).despawn_recursive();
-                tilemap.set_tile(
-                    pos.extend(1),
-                    Some(bevy_simple_tilemap::Tile {
-                        sprite_index: 16,
-                        ..default()
-                    }),
-                );
+                set_pit_tile(&mut tilemap, *pos, true);
+                pit_filled.send(PitFilled(*pos));
             }
         }
     }
@@ -61,16 +110,18 @@ pub struct DespawnSokobanEntityCommand(pub Entity);
 
 impl Command for DespawnSokobanEntityCommand {
     fn apply(self, world: &mut World) {
-        let (pos, history, previous, kind) = world
+        let (pos, history, previous, kind, id) = world
             .query::<(
                 &TilePos,
                 &History<TilePos>,
                 &PreviousComponent<TilePos>,
                 &EntityKind,
+                &BlockId,
             )>()
             .get(world, self.0)
             .expect("test");
-        let (pos, history, previous, kind) = (*pos, history.clone(), previous.clone(), *kind);
+        let (pos, history, previous, kind, id) =
+            (*pos, history.clone(), previous.clone(), *kind, *id);
 
         let level_entity = world
             .query_filtered::<Entity, With<LevelRoot>>()
@@ -83,6 +134,7 @@ impl Command for DespawnSokobanEntityCommand {
                 pos,
                 history,
                 kind,
+                id,
                 previous,
                 level_entity,
             };
@@ -92,57 +144,209 @@ impl Command for DespawnSokobanEntityCommand {
     }
 }
 
+/// Blocks and pits spawned and despawned by the events of one frame.
+///
+/// Commands are only applied after all events are handled, so entities from earlier events
+/// aren't in the queries yet.
+#[derive(Default)]
+struct FrameEntities {
+    blocks: Vec<(BlockId, Entity)>,
+    pits: Vec<(TilePos, Entity)>,
+    despawned: Vec<Entity>,
+}
+
+impl FrameEntities {
+    fn block(&self, id: BlockId, blocks: &Query<(Entity, &BlockId)>) -> Option<Entity> {
+        self.blocks
+            .iter()
+            .map(|(block, entity)| (*entity, block))
+            .chain(blocks.iter())
+            .find(|(entity, block)| **block == id && !self.despawned.contains(entity))
+            .map(|(entity, _)| entity)
+    }
+
+    fn pit(&self, pos: TilePos, pits: &Query<(Entity, &TilePos), With<Pit>>) -> Option<Entity> {
+        self.pits
+            .iter()
+            .map(|(pit, entity)| (*entity, pit))
+            .chain(pits.iter())
+            .find(|(entity, pit)| **pit == pos && !self.despawned.contains(entity))
+            .map(|(entity, _)| entity)
+    }
+
+    fn despawn(&mut self, cmds: &mut Commands, entity: Option<Entity>) {
+        if let Some(entity) = entity {
+            cmds.entity(entity).despawn_recursive();
+            self.despawned.push(entity);
+        }
+    }
+}
+
+/// Spawns a block that fell into a pit at `pos` and opens the pit again.
+fn respawn_from_pit(
+    cmds: &mut Commands,
+    tilemap: &mut TileMap,
+    frame: &mut FrameEntities,
+    despawn: &HistoryStore,
+    pos: TilePos,
+    history: History<TilePos>,
+) {
+    cmds.entity(despawn.level_entity).with_children(|parent| {
+        let block = parent
+            .spawn((
+                pos,
+                history,
+                despawn.previous.clone(),
+                despawn.kind,
+                despawn.id,
+                FromPit(despawn.pos),
+            ))
+            .id();
+        let pit = parent.spawn((despawn.pos, EntityKind::Pit)).id();
+        frame.blocks.push((despawn.id, block));
+        frame.pits.push((despawn.pos, pit));
+    });
+    set_pit_tile(tilemap, despawn.pos, false);
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn rewind(
     mut cmds: Commands,
     mut history_events: EventReader<HistoryEvent>,
     mut command_history: ResMut<DespawnHistory>,
+    mut reset_history: ResMut<ResetHistory>,
     current_time: Res<CurrentTime>,
-    mut tilemap: Query<&mut TileMap>,
+    mut tilemap: Query<&mut TileMap, With<GroundTiles>>,
+    blocks: Query<(Entity, &BlockId)>,
+    pits: Query<(Entity, &TilePos), With<Pit>>,
 ) {
     let Ok(mut tilemap) = tilemap.get_single_mut() else {
         return;
     };
+    let mut frame = FrameEntities::default();
+    // Several events can arrive in one frame, the time is only updated afterwards
+    let mut now = *current_time;
     for ev in history_events.read() {
         match ev {
-            HistoryEvent::Record => {}
+            HistoryEvent::Record => reset_history.redo.clear(),
             HistoryEvent::Rewind => {
                 while let Some((time, despawn)) = command_history.pop() {
-                    if time == **current_time {
-                        cmds.entity(despawn.level_entity).with_children(|parent| {
-                            parent.spawn((
-                                despawn.pos,
-                                despawn.history,
-                                despawn.previous,
-                                despawn.kind,
-                            ));
-                        });
-                        cmds.entity(despawn.level_entity).with_children(|parent| {
-                            parent.spawn((despawn.pos, EntityKind::Pit));
-                        });
-                        tilemap.set_tile(
-                            despawn.pos.extend(1),
-                            Some(bevy_simple_tilemap::Tile {
-                                sprite_index: 18,
-                                ..default()
-                            }),
+                    if time == *now {
+                        let (pos, history) = (despawn.pos, despawn.history.clone());
+                        respawn_from_pit(
+                            &mut cmds,
+                            &mut tilemap,
+                            &mut frame,
+                            &despawn,
+                            pos,
+                            history,
                         );
                     } else {
                         command_history.push((time, despawn));
                         break;
                     }
                 }
+
+                // Undoing a reset drops the blocks back into their pits
+                if reset_history
+                    .undo
+                    .last()
+                    .is_some_and(|(time, _)| time + 1 == *now)
+                {
+                    let (time, respawned) = reset_history.undo.pop().unwrap();
+                    for respawned in respawned {
+                        // The entities the reset spawned may have been despawned and respawned
+                        // since, so they are looked up by id and position
+                        let block = frame.block(respawned.despawn.id, &blocks);
+                        if block.is_none() {
+                            log::warn!(
+                                "Block {:?} missing while undoing reset",
+                                respawned.despawn.id
+                            );
+                        }
+                        frame.despawn(&mut cmds, block);
+                        let pit = frame.pit(respawned.despawn.pos, &pits);
+                        frame.despawn(&mut cmds, pit);
+                        set_pit_tile(&mut tilemap, respawned.despawn.pos, true);
+                        command_history.push((respawned.time, respawned.despawn));
+                    }
+                    reset_history.redo.push(time);
+                }
+            }
+            // Redoing a push into a pit moves the block back onto it, where it fills the pit again
+            HistoryEvent::Redo => {
+                if reset_history.redo.last() == Some(&*now) {
+                    reset_history.redo.pop();
+                    // Redo doesn't move them like reset does, so they start where reset put them
+                    let respawned = command_history
+                        .drain(..)
+                        .map(|(time, despawn)| {
+                            let mut history = despawn.history.clone();
+                            let start = history.first().map_or(despawn.pos, |(_, pos)| *pos);
+                            history.push((*now, despawn.pos));
+                            respawn_from_pit(
+                                &mut cmds,
+                                &mut tilemap,
+                                &mut frame,
+                                &despawn,
+                                start,
+                                history,
+                            );
+                            RespawnedBlock { time, despawn }
+                        })
+                        .collect();
+                    reset_history.undo.push((*now, respawned));
+                }
+            }
+            // Blocks come out of their pits, the reset of their history moves them to the start
+            HistoryEvent::Reset => {
+                reset_history.redo.clear();
+                let respawned = command_history
+                    .drain(..)
+                    .map(|(time, despawn)| {
+                        let (pos, history) = (despawn.pos, despawn.history.clone());
+                        respawn_from_pit(
+                            &mut cmds,
+                            &mut tilemap,
+                            &mut frame,
+                            &despawn,
+                            pos,
+                            history,
+                        );
+                        RespawnedBlock { time, despawn }
+                    })
+                    .collect();
+                reset_history.undo.push((*now, respawned));
             }
-            HistoryEvent::Reset => {}
         }
+        now.apply(ev);
     }
 }
 
+/// A block a reset took out of its pit.
 #[derive(Reflect)]
+pub struct RespawnedBlock {
+    /// When the block fell into the pit.
+    pub time: usize,
+    pub despawn: HistoryStore,
+}
+
+/// Resets that took blocks out of pits, by the time they happened.
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct ResetHistory {
+    undo: Vec<(usize, Vec<RespawnedBlock>)>,
+    /// Undone resets, the last one is redone first.
+    redo: Vec<usize>,
+}
+
+#[derive(Reflect, Clone)]
 pub struct HistoryStore {
     pub pos: TilePos,
     pub history: History<TilePos>,
     pub previous: PreviousComponent<TilePos>,
     pub kind: EntityKind,
+    pub id: BlockId,
     pub level_entity: Entity,
 }
 
@@ -150,62 +354,156 @@ pub struct HistoryStore {
 #[reflect(Resource)]
 pub struct DespawnHistory(Vec<(usize, HistoryStore)>);
 
+fn reset_despawn_history(
+    mut despawn_history: ResMut<DespawnHistory>,
+    mut reset_history: ResMut<ResetHistory>,
+) {
+    despawn_history.clear();
+    *reset_history = ResetHistory::default();
+}
+
 fn check_win(
     player_q: Query<&TilePos, With<Player>>,
-    entity_q: Query<(&TilePos, &EntityKind), Without<Player>>,
     collision: Res<CollisionMap>,
+    level_data: LevelData,
+    stats: Res<LevelStats>,
+    replay: Option<Res<Replay>>,
+    mut level_completed: EventWriter<LevelCompleted>,
 ) {
     let Ok(player_pos) = player_q.get_single() else {
         return;
     };
 
-    let mut check_for_reachable = Vec::new();
-    for (entity_pos, kind) in entity_q.iter() {
-        let entity_pos = **entity_pos;
-        match kind {
-            EntityKind::Pullable => {
-                for dir in CARDINALS_DIR.iter() {
-                    let dest = entity_pos + IVec2::from(*dir);
-                    let dest2 = dest + IVec2::from(*dir);
-                    if !(collision.is_blocked(dest, true)
-                        || collision.is_blocked(dest2, true)
-                        || collision.is_blocked(dest, false))
-                    {
-                        check_for_reachable.push(dest);
-                    }
-                }
-            }
-            EntityKind::Pushable => {
-                for dir in CARDINALS_DIR.iter() {
-                    let dest = entity_pos + IVec2::from(*dir);
-                    let opp = entity_pos + IVec2::from(dir.opposite());
-                    if !(collision.is_blocked(dest, true) || collision.is_blocked(opp, false)) {
-                        check_for_reachable.push(dest);
-                    }
-                }
+    if collision.is_won(**player_pos) {
+        level_completed.send(LevelCompleted {
+            level: level_data.current_level().clone(),
+            moves: stats.moves,
+            undos: stats.undos,
+            replayed: replay.is_some(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::history::{
+        HandlePreviousComponent, HistoryBundle, HistoryComponentPlugin, HistoryPlugin,
+        PreviousComponentPlugin,
+    };
+
+    /// Pits get their marker from [`entity_kind_components`](crate::game::entity_kind_components),
+    /// which needs the game's assets.
+    fn mark_pits(mut cmds: Commands, pits: Query<(Entity, &EntityKind), Added<EntityKind>>) {
+        for (entity, kind) in pits.iter() {
+            if matches!(kind, EntityKind::Pit) {
+                cmds.entity(entity).insert(Pit);
             }
-            _ => {}
         }
     }
 
-    let mut queue = Vec::new();
-    queue.push(**player_pos);
-    let mut visited = HashSet::new();
+    /// A block at (1, 0) next to a pit at (2, 0).
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            HistoryPlugin,
+            HistoryComponentPlugin::<TilePos>::default(),
+            PreviousComponentPlugin::<TilePos>::default(),
+        ))
+        .init_resource::<DespawnHistory>()
+        .init_resource::<ResetHistory>()
+        .init_resource::<MoveQueue>()
+        .add_event::<PitFilled>()
+        .add_systems(
+            Update,
+            (
+                (rewind, apply_deferred).chain().before(HandleHistoryEvents),
+                (mark_pits, apply_deferred, despawn_on_pit)
+                    .chain()
+                    .after(HandlePreviousComponent),
+            ),
+        );
+
+        let world = app.world_mut();
+        world.spawn((TileMap::default(), GroundTiles));
+        world.spawn(LevelRoot).with_children(|parent| {
+            parent.spawn((
+                TilePos::new(1, 0),
+                EntityKind::Pushable,
+                HistoryBundle::<TilePos>::default(),
+                BlockId(0),
+            ));
+            parent.spawn((TilePos::new(2, 0), EntityKind::Pit));
+        });
+        app.update();
+        app
+    }
 
-    while let Some(next) = queue.pop() {
-        visited.insert(next);
-        for dir in CARDINALS.iter() {
-            let dest = next + *dir;
-            if !collision.is_blocked(dest, true) && !visited.contains(&dest) {
-                queue.push(dest);
-            }
+    /// Records the turn and moves the block onto the pit, like pushing it there does.
+    fn push_into_pit(app: &mut App) {
+        app.world_mut().send_event(HistoryEvent::Record);
+        let mut blocks = app.world_mut().query::<(&mut TilePos, &BlockId)>();
+        for (mut pos, _) in blocks.iter_mut(app.world_mut()) {
+            *pos = TilePos::new(2, 0);
         }
+        app.update();
     }
 
-    if check_for_reachable
-        .iter()
-        .all(|check| !visited.contains(check))
-    {
-        log::info!("WIN!");
+    fn send(app: &mut App, ev: HistoryEvent) {
+        app.world_mut().send_event(ev);
+        app.update();
+    }
+
+    fn blocks(app: &mut App) -> Vec<TilePos> {
+        let mut blocks = app.world_mut().query_filtered::<&TilePos, With<BlockId>>();
+        blocks.iter(app.world()).copied().collect()
+    }
+
+    fn pits(app: &mut App) -> usize {
+        let mut pits = app.world_mut().query::<&Pit>();
+        pits.iter(app.world()).count()
+    }
+
+    #[test]
+    fn undo_reset_after_filling_pit_again() {
+        let mut app = app();
+        push_into_pit(&mut app);
+        assert!(blocks(&mut app).is_empty());
+        assert_eq!(pits(&mut app), 0);
+
+        send(&mut app, HistoryEvent::Reset);
+        assert_eq!(blocks(&mut app), vec![TilePos::new(1, 0)]);
+        assert_eq!(pits(&mut app), 1);
+
+        // The block the reset spawned is despawned and a new one is spawned by the undo
+        push_into_pit(&mut app);
+        assert!(blocks(&mut app).is_empty());
+        send(&mut app, HistoryEvent::Rewind);
+        assert_eq!(blocks(&mut app), vec![TilePos::new(1, 0)]);
+        assert_eq!(pits(&mut app), 1);
+
+        send(&mut app, HistoryEvent::Rewind);
+        assert!(blocks(&mut app).is_empty());
+        assert_eq!(pits(&mut app), 0);
+        assert_eq!(app.world().resource::<DespawnHistory>().len(), 1);
+
+        send(&mut app, HistoryEvent::Rewind);
+        assert_eq!(blocks(&mut app), vec![TilePos::new(1, 0)]);
+        assert_eq!(pits(&mut app), 1);
+    }
+
+    #[test]
+    fn undo_reset_in_the_same_frame_as_refill_undo() {
+        let mut app = app();
+        push_into_pit(&mut app);
+        send(&mut app, HistoryEvent::Reset);
+        push_into_pit(&mut app);
+
+        // Jumping back through the timeline sends every undo at once
+        app.world_mut().send_event(HistoryEvent::Rewind);
+        send(&mut app, HistoryEvent::Rewind);
+        assert!(blocks(&mut app).is_empty());
+        assert_eq!(pits(&mut app), 0);
+        assert_eq!(app.world().resource::<DespawnHistory>().len(), 1);
     }
 }
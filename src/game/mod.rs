@@ -1,23 +1,48 @@
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::cleanup;
 
 use self::{
+    bindings::Bindings,
     history::{History, HistoryEvent, PreviousComponent},
     level::{LevelData, LevelLoader, Levels},
     mechanics::Pit,
+    pack::LevelPacks,
+    player::{MoveQueue, PlayerInput},
+    repeat::{KeyRepeat, RepeatPolicy},
+    sound::SoundAssets,
+    tween::{FromPit, MoveTween, ScaleTween},
 };
 
+pub mod animation;
+pub mod bindings;
+pub mod click_move;
 pub mod collision;
+pub mod editor;
 pub mod history;
 pub mod level;
 pub mod level_select;
 pub mod level_transition;
+pub mod lurd;
 pub mod mechanics;
+pub mod pack;
 pub mod player;
+pub mod puzzle;
+pub mod repeat;
+pub mod replay;
+pub mod save;
+pub mod settings;
+pub mod snapshot;
+pub mod solver;
+pub mod sound;
+pub mod timeline;
+pub mod tween;
 pub mod util;
+pub mod win;
+pub mod xsb;
 
 pub struct GamePlugin;
 
@@ -25,19 +50,38 @@ impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((
             InputManagerPlugin::<GameAction>::default(),
+            bindings::BindingsPlugin,
+            settings::SettingsPlugin,
             level_select::LevelSelectPlugin,
+            editor::EditorPlugin,
             level_transition::LevelTransitionPlugin,
             player::PlayerPlugin,
+            click_move::ClickMovePlugin,
+            animation::AnimationPlugin,
             collision::CollisionPlugin,
             level::LevelPlugin,
+            pack::PackPlugin,
             history::HistoryPlugin,
             history::HistoryComponentPlugin::<TilePos>::default(),
             history::PreviousComponentPlugin::<TilePos>::default(),
             mechanics::MechanicsPlugin,
+            (
+                win::WinPlugin,
+                save::SavePlugin,
+                replay::ReplayPlugin,
+                lurd::LurdPlugin,
+                snapshot::SnapshotPlugin,
+                timeline::TimelinePlugin,
+                tween::TweenPlugin,
+                sound::SoundPlugin,
+            ),
             cleanup::StateCleanupPlugin::<GameState>::default(),
         ));
         app.register_asset_loader(LevelLoader)
+            .register_asset_loader(xsb::XsbLoader)
             .init_asset::<Levels>();
+        app.init_resource::<RepeatPolicy>()
+            .register_type::<RepeatPolicy>();
         app.register_type::<TilePos>()
             .register_type::<Dir>()
             .register_type::<History<TilePos>>()
@@ -45,16 +89,21 @@ impl Plugin for GamePlugin {
             .register_type::<EntityKind>();
         app.init_state::<GameState>()
             .add_loading_state(
-                LoadingState::new(GameState::AssetLoading)
-                    .continue_to_state(GameState::LevelSelect),
+                LoadingState::new(GameState::AssetLoading).continue_to_state(GameState::PackSelect),
             )
             .configure_loading_state(
-                LoadingStateConfig::new(GameState::AssetLoading).load_collection::<GameAssets>(),
+                LoadingStateConfig::new(GameState::AssetLoading)
+                    .load_collection::<GameAssets>()
+                    .load_collection::<SoundAssets>(),
             );
         app.add_systems(Startup, setup)
             .add_systems(
                 Update,
-                (history, navigation).run_if(in_state(GameState::Play)),
+                (
+                    (history, history::jump_to_turn).in_set(PlayerInput),
+                    navigation,
+                )
+                    .run_if(in_state(GameState::Play)),
             )
             .add_systems(
                 PostUpdate,
@@ -73,15 +122,24 @@ impl Plugin for GamePlugin {
 pub enum GameState {
     #[default]
     AssetLoading,
+    PackSelect,
     LevelSelect,
     LevelTransition,
     Play,
+    Won,
+    Editor,
+    Settings,
 }
 
 #[derive(Resource, Default, AssetCollection, Debug)]
 pub struct GameAssets {
     #[asset(path = "player.png")]
     pub player: Handle<Image>,
+    /// Every [`animation::AnimationState`] of the player, one row per [`Dir`].
+    #[asset(path = "player_sheet.png")]
+    pub player_sheet: Handle<Image>,
+    #[asset(texture_atlas(tile_size_x = 16, tile_size_y = 16, columns = 4, rows = 20))]
+    pub player_layout: Handle<TextureAtlasLayout>,
     #[asset(path = "pushable.png")]
     pub pushable: Handle<Image>,
     #[asset(path = "pullable.png")]
@@ -92,8 +150,8 @@ pub struct GameAssets {
     pub tiles: Handle<Image>,
     #[asset(path = "button.png")]
     pub button: Handle<Image>,
-    #[asset(path = "test.levels.ron")]
-    pub levels: Handle<Levels>,
+    #[asset(path = "levels/index.packs.ron")]
+    pub packs: Handle<LevelPacks>,
 }
 
 pub fn entity_kind_components(
@@ -133,21 +191,52 @@ pub fn entity_kind_components(
         }
     }
 }
+
+/// Moves sprites to their [`TilePos`], tweened over the time between two moves unless the
+/// entity was just spawned.
 pub fn copy_pos_to_transform(
+    mut cmds: Commands,
     level_data: LevelData,
-    mut query: Query<(&TilePos, &mut Transform, Option<&SpriteOffset>), Changed<TilePos>>,
+    move_queue: Res<MoveQueue>,
+    mut query: Query<
+        (
+            Entity,
+            Ref<TilePos>,
+            &mut Transform,
+            Option<&SpriteOffset>,
+            Option<&FromPit>,
+        ),
+        Changed<TilePos>,
+    >,
 ) {
-    for (pos, mut transform, offset) in query.iter_mut() {
-        let new_pos = pos.wpos() + offset.map_or(Vec2::ZERO, |offset| **offset);
+    for (entity, pos, mut transform, offset, from_pit) in query.iter_mut() {
+        let offset = offset.map_or(Vec2::ZERO, |offset| **offset);
+        let new_pos = pos.wpos() + offset;
+        // Depth follows the tile right away so sprites overlap correctly while moving
+        transform.translation.z = level_data.size().y as f32 - pos.y as f32;
 
-        transform.translation = new_pos.extend(level_data.size().y as f32 - pos.y as f32);
+        let from = if let Some(FromPit(pit)) = from_pit {
+            transform.scale = Vec3::splat(0.);
+            cmds.entity(entity)
+                .remove::<FromPit>()
+                .insert(ScaleTween::new(0., 1., move_queue.cooldown * 2));
+            pit.wpos() + offset
+        } else if pos.is_added() {
+            transform.translation = new_pos.extend(transform.translation.z);
+            continue;
+        } else {
+            transform.translation.truncate()
+        };
+        transform.translation = from.extend(transform.translation.z);
+        cmds.entity(entity)
+            .insert(MoveTween::new(from, new_pos, move_queue.cooldown));
     }
 }
 
-fn setup(mut cmds: Commands) {
+fn setup(mut cmds: Commands, bindings: Res<Bindings>) {
     cmds.spawn((
         (InputManagerBundle::<GameAction> {
-            input_map: game_actions(),
+            input_map: bindings.game_map(),
             ..default()
         },),
         Name::new("GameActions"),
@@ -164,39 +253,67 @@ fn navigation(
 
     if actions.just_pressed(&GameAction::ToLevelSelect) {
         next_state.set(GameState::LevelSelect);
+    } else if actions.just_pressed(&GameAction::ToEditor) {
+        next_state.set(GameState::Editor);
     }
 }
 
+#[derive(Default)]
+struct HistoryRepeat {
+    undo: KeyRepeat,
+    redo: KeyRepeat,
+}
+
 fn history(
+    mut repeat: Local<HistoryRepeat>,
     actions: Query<&ActionState<GameAction>>,
     mut history_events: EventWriter<HistoryEvent>,
+    policy: Res<RepeatPolicy>,
+    time: Res<Time>,
 ) {
     let Ok(actions) = actions.get_single() else {
         return;
     };
-    if actions.just_pressed(&GameAction::Undo) {
+    let undo = repeat
+        .undo
+        .tick(actions.pressed(&GameAction::Undo), time.delta(), &policy);
+    let redo = repeat
+        .redo
+        .tick(actions.pressed(&GameAction::Redo), time.delta(), &policy);
+    if undo {
         history_events.send(HistoryEvent::Rewind);
+    } else if redo {
+        history_events.send(HistoryEvent::Redo);
     } else if actions.just_pressed(&GameAction::Reset) {
         history_events.send(HistoryEvent::Reset);
     }
 }
 
-#[derive(Actionlike, Clone, Copy, Hash, Debug, PartialEq, Eq, Reflect)]
+#[derive(
+    Actionlike,
+    Clone,
+    Copy,
+    Hash,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Reflect,
+    Serialize,
+    Deserialize,
+)]
 pub enum GameAction {
     Undo,
+    Redo,
     Reset,
     ToLevelSelect,
-}
-
-fn game_actions() -> InputMap<GameAction> {
-    use GameAction::*;
-    let mut input_map: InputMap<GameAction> = InputMap::default();
-
-    input_map.insert(Undo, KeyCode::KeyE);
-    input_map.insert(Reset, KeyCode::KeyR);
-    input_map.insert(ToLevelSelect, KeyCode::KeyG);
-
-    input_map
+    ToEditor,
+    CopySolution,
+    PasteSolution,
+    ToggleMute,
+    VolumeDown,
+    VolumeUp,
 }
 
 #[derive(Component, Default, Clone, Copy, Debug, PartialEq, Eq, Deref, DerefMut, Reflect)]
@@ -216,7 +333,7 @@ impl TilePos {
     }
 }
 
-#[derive(Debug, Copy, Clone, Reflect)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Reflect)]
 pub enum Dir {
     Up,
     Right,
@@ -250,7 +367,7 @@ impl From<Dir> for IVec2 {
 #[derive(Component, Deref, DerefMut, Default, Reflect, Clone, Copy)]
 pub struct SpriteOffset(pub Vec2);
 
-#[derive(Debug, Copy, Clone, Component, Reflect, PartialEq)]
+#[derive(Debug, Copy, Clone, Component, Reflect, PartialEq, Eq, Hash)]
 pub enum EntityKind {
     Wall,
     Pit,
@@ -258,3 +375,9 @@ pub enum EntityKind {
     Pullable,
     Pushable,
 }
+
+impl EntityKind {
+    pub fn fills_pit(&self) -> bool {
+        matches!(self, EntityKind::Pushable)
+    }
+}
@@ -0,0 +1,134 @@
+use std::future::Future;
+
+use bevy::{
+    asset::{AssetLoader, AsyncReadExt, ParseAssetPathError},
+    prelude::*,
+    reflect::TypePath,
+    utils::ConditionalSendFuture,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::level::Levels;
+
+pub struct PackPlugin;
+
+impl Plugin for PackPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_asset_loader(LevelPacksLoader)
+            .init_asset::<LevelPacks>()
+            .init_resource::<CurrentPack>()
+            .register_type::<CurrentPack>();
+    }
+}
+
+/// Levels file listed in the pack index.
+#[derive(Debug, Clone, Reflect)]
+pub struct LevelPack {
+    /// Path of the levels file as written in the index, used to keep track of progress.
+    pub id: String,
+    pub name: String,
+    pub levels: Handle<Levels>,
+}
+
+/// All packs listed in the pack index, in the order they are shown in the pack picker.
+///
+/// Packs are listed in an index file instead of loading the whole folder
+/// because folder loading isn't supported on the web.
+#[derive(TypePath, Debug, Deref, Asset)]
+pub struct LevelPacks(pub Vec<LevelPack>);
+
+impl LevelPacks {
+    pub fn get_by_id(&self, id: &str) -> Option<&LevelPack> {
+        self.iter().find(|pack| pack.id == id)
+    }
+}
+
+/// Pack the current level belongs to.
+#[derive(Resource, Reflect, Default, Debug, Clone)]
+#[reflect(Resource)]
+pub struct CurrentPack {
+    pub id: String,
+    pub levels: Handle<Levels>,
+}
+
+impl From<&LevelPack> for CurrentPack {
+    fn from(pack: &LevelPack) -> Self {
+        CurrentPack {
+            id: pack.id.clone(),
+            levels: pack.levels.clone_weak(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PackEntry {
+    name: String,
+    /// Relative to the index file.
+    path: String,
+}
+
+#[derive(Default)]
+pub struct LevelPacksLoader;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum LevelPacksLoaderError {
+    #[error("Could not read the file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse the ron: {0}")]
+    RonError(#[from] ron::error::SpannedError),
+    #[error("Pack {name:?} has an invalid path: {source}")]
+    InvalidPath {
+        name: String,
+        source: ParseAssetPathError,
+    },
+    #[error("Pack index lists no packs")]
+    Empty,
+}
+
+impl AssetLoader for LevelPacksLoader {
+    type Asset = LevelPacks;
+    type Settings = ();
+    type Error = LevelPacksLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> impl ConditionalSendFuture
+           + Future<Output = Result<<Self as AssetLoader>::Asset, <Self as AssetLoader>::Error>>
+    {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let entries = ron::de::from_bytes::<Vec<PackEntry>>(&bytes)?;
+            if entries.is_empty() {
+                return Err(LevelPacksLoaderError::Empty);
+            }
+
+            let mut packs = Vec::new();
+            for entry in entries {
+                let path = load_context
+                    .asset_path()
+                    .resolve_embed(&entry.path)
+                    .map_err(|source| LevelPacksLoaderError::InvalidPath {
+                        name: entry.name.clone(),
+                        source,
+                    })?;
+                packs.push(LevelPack {
+                    levels: load_context.load(path),
+                    id: entry.path,
+                    name: entry.name,
+                });
+            }
+
+            Ok(LevelPacks(packs))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["packs.ron"]
+    }
+}
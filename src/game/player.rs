@@ -1,11 +1,15 @@
-use std::ops::AddAssign;
+use std::{collections::VecDeque, ops::AddAssign, time::Duration};
 
 use bevy::{ecs::world::Command, log, prelude::*};
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use super::{
+    animation::PlayerAnimation,
+    bindings::Bindings,
     collision::CollisionMap,
     history::{HandleHistoryEvents, HistoryBundle, HistoryEvent},
+    repeat::{KeyRepeat, RepeatPolicy},
     Dir, GameAssets, GameState, SpriteOffset, TilePos,
 };
 
@@ -14,20 +18,66 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(InputManagerPlugin::<PlayerAction>::default())
+            .add_event::<MoveRequest>()
+            .add_event::<PlayerMoved>()
+            .add_event::<PlayerBumped>()
+            .init_resource::<MoveQueue>()
+            .register_type::<MoveQueue>()
             .add_systems(Startup, setup)
+            .add_systems(
+                OnTransition {
+                    exited: GameState::LevelTransition,
+                    entered: GameState::Play,
+                },
+                clear_move_queue,
+            )
             .add_systems(
                 Update,
-                player_movement
+                (player_input.in_set(PlayerInput), player_movement)
+                    .chain()
                     .before(HandleHistoryEvents)
                     .run_if(in_state(GameState::Play)),
             );
     }
 }
 
+/// Systems that turn keyboard input into gameplay events.
+#[derive(SystemSet, Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct PlayerInput;
+
+/// Direction the player tries to move in, either from input or from a replay.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MoveRequest(pub Dir);
+
+/// The player moved, sent alongside [`HistoryEvent::Record`].
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerMoved {
+    pub direction: Dir,
+    pub pushed: bool,
+    pub pulled: bool,
+}
+
+/// The player tried to move but something blocked the way.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerBumped(pub Dir);
+
 #[derive(Component, Clone)]
 pub struct Player;
 
-#[derive(Actionlike, Clone, Copy, Hash, Debug, PartialEq, Eq, Reflect)]
+#[derive(
+    Actionlike,
+    Clone,
+    Copy,
+    Hash,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Reflect,
+    Serialize,
+    Deserialize,
+)]
 pub enum PlayerAction {
     Up,
     Right,
@@ -62,7 +112,9 @@ impl SpawnPlayer {
 
 impl Command for SpawnPlayer {
     fn apply(self, world: &mut World) {
-        let texture = world.resource::<GameAssets>().player.clone();
+        let assets = world.resource::<GameAssets>();
+        let (texture, layout) = (assets.player_sheet.clone(), assets.player_layout.clone());
+        let animation = PlayerAnimation::default();
         world
             .entity_mut(self.tilemap_entity)
             .with_children(|child_builder| {
@@ -75,6 +127,11 @@ impl Command for SpawnPlayer {
                         texture,
                         ..default()
                     },
+                    TextureAtlas {
+                        layout,
+                        index: animation.index(),
+                    },
+                    animation,
                     SpriteOffset(Vec2::Y * 4.),
                     MovementTimer::default(),
                 ));
@@ -82,46 +139,89 @@ impl Command for SpawnPlayer {
     }
 }
 
-fn setup(mut cmds: Commands) {
+fn setup(mut cmds: Commands, bindings: Res<Bindings>) {
     cmds.spawn((
         (InputManagerBundle::<PlayerAction> {
-            input_map: player_actions(),
+            input_map: bindings.player_map(),
             ..default()
         },),
         Name::new("PlayerActions"),
     ));
 }
 
-fn player_actions() -> InputMap<PlayerAction> {
-    use PlayerAction::*;
-    let mut input_map = InputMap::default();
+/// Repeats movement while a direction is held, following the [`RepeatPolicy`].
+#[derive(Clone, Debug, Default, Component)]
+pub struct MovementTimer {
+    pub repeat: KeyRepeat,
+    /// Direction pressed last that is still held, it is the one that repeats.
+    pub held: Option<PlayerAction>,
+}
 
-    input_map.insert(Up, KeyCode::KeyW);
-    input_map.insert(Right, KeyCode::KeyD);
-    input_map.insert(Down, KeyCode::KeyS);
-    input_map.insert(Left, KeyCode::KeyA);
+/// Direction presses waiting for the previous move to finish, so quick presses aren't lost.
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct MoveQueue {
+    /// Presses are dropped while this many moves are waiting.
+    pub capacity: usize,
+    /// Time between two moves.
+    pub cooldown: Duration,
+    queue: VecDeque<Dir>,
+    ready_in: Duration,
+}
 
-    input_map
+impl Default for MoveQueue {
+    fn default() -> Self {
+        MoveQueue {
+            capacity: 3,
+            cooldown: Duration::from_millis(100),
+            queue: VecDeque::new(),
+            ready_in: Duration::ZERO,
+        }
+    }
 }
 
-#[derive(Clone, Debug, Component, Deref, DerefMut)]
-pub struct MovementTimer(pub Timer);
+impl MoveQueue {
+    pub fn push(&mut self, dir: Dir) {
+        if self.queue.len() < self.capacity {
+            self.queue.push_back(dir);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
 
-impl Default for MovementTimer {
-    fn default() -> MovementTimer {
-        MovementTimer(Timer::from_seconds(0.2, TimerMode::Once))
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    /// Returns the next move once the previous one cooled down.
+    fn tick(&mut self, delta: Duration) -> Option<Dir> {
+        self.ready_in = self.ready_in.saturating_sub(delta);
+        if !self.ready_in.is_zero() {
+            return None;
+        }
+        let dir = self.queue.pop_front()?;
+        self.ready_in = self.cooldown;
+        Some(dir)
     }
 }
 
-pub fn player_movement(
-    mut player_q: Query<(Entity, &mut MovementTimer), With<Player>>,
-    mut dynamic_entities: Query<&mut TilePos>,
-    mut history_events: EventWriter<HistoryEvent>,
+fn clear_move_queue(mut queue: ResMut<MoveQueue>) {
+    queue.clear();
+    queue.ready_in = Duration::ZERO;
+}
+
+pub fn player_input(
+    mut player_q: Query<&mut MovementTimer, With<Player>>,
+    mut move_requests: EventWriter<MoveRequest>,
+    mut history_events: EventReader<HistoryEvent>,
+    mut queue: ResMut<MoveQueue>,
     player_actions: Query<&ActionState<PlayerAction>>,
+    policy: Res<RepeatPolicy>,
     time: Res<Time>,
-    collision: Res<CollisionMap>,
 ) {
-    let Ok((player_entity, mut movement_timer)) = player_q.get_single_mut() else {
+    let Ok(mut movement_timer) = player_q.get_single_mut() else {
         return;
     };
 
@@ -129,44 +229,86 @@ pub fn player_movement(
         .get_single()
         .expect("Player input map should exist");
 
-    movement_timer.tick(time.delta());
+    // Moves queued before an undo or reset shouldn't play after it
+    if history_events
+        .read()
+        .any(|ev| !matches!(ev, HistoryEvent::Record))
+    {
+        queue.clear();
+    }
+
+    let just_pressed = player_actions.get_just_pressed();
+    for action in just_pressed.iter() {
+        queue.push(Dir::from(*action));
+    }
+    if let Some(action) = just_pressed.last() {
+        movement_timer.held = Some(*action);
+    }
+    if movement_timer
+        .held
+        .is_some_and(|held| !player_actions.pressed(&held))
+    {
+        movement_timer.held = player_actions.get_pressed().first().copied();
+    }
 
-    if !movement_timer.finished() {
-        return;
+    let held = movement_timer.held;
+    let repeat = movement_timer
+        .repeat
+        .tick(held.is_some(), time.delta(), &policy);
+    // Presses were queued above, holding a key only adds a move when none are waiting
+    if let Some(held) = held.filter(|_| repeat && just_pressed.is_empty() && queue.is_empty()) {
+        queue.push(Dir::from(held));
     }
 
+    if let Some(dir) = queue.tick(time.delta()) {
+        move_requests.send(MoveRequest(dir));
+    }
+}
+
+pub fn player_movement(
+    player_q: Query<Entity, With<Player>>,
+    mut dynamic_entities: Query<&mut TilePos>,
+    mut move_requests: EventReader<MoveRequest>,
+    mut history_events: EventWriter<HistoryEvent>,
+    mut player_moved: EventWriter<PlayerMoved>,
+    mut player_bumped: EventWriter<PlayerBumped>,
+    collision: Res<CollisionMap>,
+) {
+    let Ok(player_entity) = player_q.get_single() else {
+        return;
+    };
+
+    // The collision map only catches up with moved entities once per frame
+    let Some(MoveRequest(direction)) = move_requests.read().next().copied() else {
+        return;
+    };
+    move_requests.clear();
+
     let player_pos = dynamic_entities
         .get(player_entity)
         .expect("Player always has tile pos")
         .0;
 
-    let mut moved = false;
-    for direction in player_actions
-        .get_pressed()
-        .iter()
-        .map(|action| Dir::from(*action))
-    {
-        movement_timer.reset();
-
-        match collision.player_push_collision(player_entity, player_pos, direction) {
-            super::collision::CollisionResult::Push(push) => {
-                moved = true;
-                let dir_vec = IVec2::from(direction);
-                for e in push {
-                    dynamic_entities
-                        .get_mut(e)
-                        .expect("Every entity in collision map has tile pos")
-                        .0
-                        .add_assign(dir_vec);
-                }
-            }
-            super::collision::CollisionResult::Collision => {
-                log::debug!("Can't move");
+    match collision.player_push_collision(player_entity, player_pos, direction) {
+        super::collision::CollisionResult::Push { moving, push } => {
+            let dir_vec = IVec2::from(direction);
+            for e in moving {
+                dynamic_entities
+                    .get_mut(e)
+                    .expect("Every entity in collision map has tile pos")
+                    .0
+                    .add_assign(dir_vec);
             }
+            history_events.send(HistoryEvent::Record);
+            player_moved.send(PlayerMoved {
+                direction,
+                pushed: push.pushed.is_some(),
+                pulled: push.pulled.is_some(),
+            });
+        }
+        super::collision::CollisionResult::Collision => {
+            log::debug!("Can't move");
+            player_bumped.send(PlayerBumped(direction));
         }
-    }
-
-    if moved {
-        history_events.send(HistoryEvent::Record);
     }
 }
@@ -0,0 +1,396 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    math::IVec2,
+    utils::hashbrown::{HashMap, HashSet},
+};
+
+use crate::grid::Grid;
+
+use super::{
+    level::{Level, TileKind},
+    util::{CARDINALS, CARDINALS_DIR},
+    Dir, EntityKind,
+};
+
+/// Anything that can tell what occupies a cell of a level.
+///
+/// All push, pull and win rules are implemented on top of this, so the same logic drives both the
+/// [`CollisionMap`](super::collision::CollisionMap) of a running game and the headless
+/// [`PuzzleState`].
+pub trait Board {
+    fn size(&self) -> IVec2;
+
+    /// `None` if `pos` is outside of the board, `Some(None)` if the cell is free.
+    fn kind_at(&self, pos: IVec2) -> Option<Option<EntityKind>>;
+
+    fn is_blocked(&self, pos: IVec2, player: bool) -> bool {
+        self.kind_at(pos).is_none_or(|kind| match kind {
+            None => false,
+            Some(kind) => {
+                matches!(
+                    kind,
+                    EntityKind::Wall | EntityKind::Pullable | EntityKind::Pushable
+                ) || if player {
+                    matches!(kind, EntityKind::Pit)
+                } else {
+                    matches!(kind, EntityKind::Platform)
+                }
+            }
+        })
+    }
+
+    /// Which blocks move along if the player at `player_pos` walks in `direction`.
+    /// Returns `None` if the player can't move at all.
+    fn push(&self, player_pos: IVec2, direction: Dir) -> Option<Push> {
+        let offset = IVec2::from(direction);
+        let dest = player_pos + offset;
+        let mut push = Push::default();
+        match self.kind_at(dest)? {
+            Some(EntityKind::Pushable) => {
+                if self.is_blocked(dest + offset, false) {
+                    return None;
+                }
+                push.pushed = Some(dest);
+            }
+            Some(EntityKind::Wall | EntityKind::Pullable | EntityKind::Pit) => return None,
+            Some(EntityKind::Platform) | None => {}
+        }
+        let opp = player_pos - offset;
+        if !self.is_blocked(player_pos, false)
+            && matches!(self.kind_at(opp), Some(Some(EntityKind::Pullable)))
+        {
+            push.pulled = Some(opp);
+        }
+        Some(push)
+    }
+
+    /// All cells the player can walk to from `from` without moving any block.
+    fn reachable(&self, from: IVec2) -> HashSet<IVec2> {
+        let mut queue = vec![from];
+        let mut visited = HashSet::new();
+
+        while let Some(next) = queue.pop() {
+            visited.insert(next);
+            for dir in CARDINALS.iter() {
+                let dest = next + *dir;
+                if !self.is_blocked(dest, true) && !visited.contains(&dest) {
+                    queue.push(dest);
+                }
+            }
+        }
+
+        visited
+    }
+
+    /// Shortest walk from `from` to `to` that doesn't push or pull any block.
+    /// Returns `None` if there is no such walk.
+    fn path(&self, from: IVec2, to: IVec2) -> Option<Vec<Dir>> {
+        let mut came_from = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(next) = queue.pop_front() {
+            if next == to {
+                let mut path = Vec::new();
+                let mut pos = to;
+                while let Some(&(previous, dir)) = came_from.get(&pos) {
+                    path.push(dir);
+                    pos = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for dir in CARDINALS_DIR {
+                let dest = next + IVec2::from(dir);
+                // Walking away from a pullable block drags it along
+                let moves_block = self
+                    .push(next, dir)
+                    .is_none_or(|push| push.pulled.is_some());
+                if dest != from
+                    && !came_from.contains_key(&dest)
+                    && !self.is_blocked(dest, true)
+                    && !moves_block
+                {
+                    came_from.insert(dest, (next, dir));
+                    queue.push_back(dest);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Cells the player would have to stand on to move any block.
+    fn push_positions(&self) -> Vec<IVec2> {
+        let size = self.size();
+        let mut positions = Vec::new();
+        for pos in (0..size.y).flat_map(|y| (0..size.x).map(move |x| IVec2::new(x, y))) {
+            match self.kind_at(pos) {
+                Some(Some(EntityKind::Pullable)) => {
+                    for dir in CARDINALS_DIR.iter() {
+                        let dest = pos + IVec2::from(*dir);
+                        let dest2 = dest + IVec2::from(*dir);
+                        if !(self.is_blocked(dest, true)
+                            || self.is_blocked(dest2, true)
+                            || self.is_blocked(dest, false))
+                        {
+                            positions.push(dest);
+                        }
+                    }
+                }
+                Some(Some(EntityKind::Pushable)) => {
+                    for dir in CARDINALS_DIR.iter() {
+                        let dest = pos + IVec2::from(*dir);
+                        let opp = pos + IVec2::from(dir.opposite());
+                        if !(self.is_blocked(dest, true) || self.is_blocked(opp, false)) {
+                            positions.push(dest);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        positions
+    }
+
+    /// The level is won once the player can't reach any position from which a block can be moved.
+    fn is_won(&self, player_pos: IVec2) -> bool {
+        let reachable = self.reachable(player_pos);
+        self.push_positions()
+            .iter()
+            .all(|pos| !reachable.contains(pos))
+    }
+}
+
+/// Blocks that move together with the player, given by their position before the move.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Push {
+    pub pushed: Option<IVec2>,
+    pub pulled: Option<IVec2>,
+}
+
+impl Board for Grid<Option<EntityKind>> {
+    fn size(&self) -> IVec2 {
+        Grid::size(self)
+    }
+
+    fn kind_at(&self, pos: IVec2) -> Option<Option<EntityKind>> {
+        self.get(pos).copied()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    Moved {
+        pushed: bool,
+        pulled: bool,
+        filled_pit: bool,
+    },
+    Blocked,
+}
+
+/// A level in play, independent of any ECS world.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PuzzleState {
+    board: Grid<Option<EntityKind>>,
+    player: IVec2,
+}
+
+impl PuzzleState {
+    /// Returns `None` if the level doesn't contain exactly one player.
+    pub fn from_level(level: &Level) -> Option<Self> {
+        let mut player = None;
+        let board = Grid::populate_from(level.size.as_ivec2(), |pos| {
+            let tile = level.tiles[(pos.y * level.size.x as i32 + pos.x) as usize];
+            if matches!(tile, TileKind::Player) {
+                player = Some(player.map_or(Some(pos), |_| None));
+            }
+            tile.entity_kind()
+        });
+
+        Some(Self {
+            board,
+            player: player??,
+        })
+    }
+
+    pub fn board(&self) -> &Grid<Option<EntityKind>> {
+        &self.board
+    }
+
+    pub fn player(&self) -> IVec2 {
+        self.player
+    }
+
+    pub fn is_won(&self) -> bool {
+        self.board.is_won(self.player)
+    }
+
+    pub fn step(&mut self, direction: Dir) -> StepOutcome {
+        let Some(push) = self.board.push(self.player, direction) else {
+            return StepOutcome::Blocked;
+        };
+        let offset = IVec2::from(direction);
+
+        let mut filled_pit = false;
+        if let Some(from) = push.pushed {
+            let kind = self.board[from].take();
+            let to = from + offset;
+            if matches!(self.board[to], Some(EntityKind::Pit))
+                && kind.is_some_and(|kind| kind.fills_pit())
+            {
+                self.board[to] = None;
+                filled_pit = true;
+            } else {
+                self.board[to] = kind;
+            }
+        }
+        if let Some(from) = push.pulled {
+            self.board[from + offset] = self.board[from].take();
+        }
+        self.player += offset;
+
+        StepOutcome::Moved {
+            pushed: push.pushed.is_some(),
+            pulled: push.pulled.is_some(),
+            filled_pit,
+        }
+    }
+}
+
+/// Level from rows written top to bottom like in a levels file, shared by the tests.
+#[cfg(test)]
+pub fn test_level(rows: &[&str]) -> Level {
+    // Tiles are stored bottom to top
+    let tiles = rows
+        .iter()
+        .rev()
+        .flat_map(|row| {
+            row.bytes()
+                .map(|glyph| TileKind::from_glyph(glyph).unwrap())
+        })
+        .collect();
+    Level {
+        tiles,
+        size: bevy::math::UVec2::new(rows[0].len() as u32, rows.len() as u32),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(rows: &[&str]) -> PuzzleState {
+        PuzzleState::from_level(&test_level(rows)).unwrap()
+    }
+
+    fn moved(pushed: bool, pulled: bool, filled_pit: bool) -> StepOutcome {
+        StepOutcome::Moved {
+            pushed,
+            pulled,
+            filled_pit,
+        }
+    }
+
+    #[test]
+    fn walk() {
+        let mut state = state(&["####", "#@_#", "####"]);
+        assert_eq!(state.step(Dir::Right), moved(false, false, false));
+        assert_eq!(state.player(), IVec2::new(2, 1));
+    }
+
+    #[test]
+    fn push_until_blocked() {
+        let mut state = state(&["#####", "#@b_#", "#####"]);
+        assert_eq!(state.step(Dir::Right), moved(true, false, false));
+        assert_eq!(state.player(), IVec2::new(2, 1));
+        assert_eq!(state.board()[IVec2::new(2, 1)], None);
+        assert_eq!(state.board()[IVec2::new(3, 1)], Some(EntityKind::Pushable));
+
+        let before = state.clone();
+        assert_eq!(state.step(Dir::Right), StepOutcome::Blocked);
+        assert_eq!(state, before);
+    }
+
+    #[test]
+    fn blocked_by_walls_pits_and_pullables() {
+        let mut state = state(&["#####", "#p@O#", "##_##", "#####"]);
+        let before = state.clone();
+        for dir in [Dir::Up, Dir::Right, Dir::Left] {
+            assert_eq!(state.step(dir), StepOutcome::Blocked);
+        }
+        assert_eq!(state, before);
+    }
+
+    #[test]
+    fn pull() {
+        let mut state = state(&["######", "#p@__#", "######"]);
+        assert_eq!(state.step(Dir::Right), moved(false, true, false));
+        assert_eq!(state.player(), IVec2::new(3, 1));
+        assert_eq!(state.board()[IVec2::new(1, 1)], None);
+        assert_eq!(state.board()[IVec2::new(2, 1)], Some(EntityKind::Pullable));
+    }
+
+    #[test]
+    fn fill_pit() {
+        let mut state = state(&["######", "#@bO_#", "######"]);
+        assert_eq!(state.step(Dir::Right), moved(true, false, true));
+        assert_eq!(state.board()[IVec2::new(2, 1)], None);
+        assert_eq!(state.board()[IVec2::new(3, 1)], None);
+        // The filled pit can be walked over
+        assert_eq!(state.step(Dir::Right), moved(false, false, false));
+        assert_eq!(state.step(Dir::Right), moved(false, false, false));
+        assert_eq!(state.player(), IVec2::new(4, 1));
+    }
+
+    #[test]
+    fn reachable_stops_at_blocks() {
+        let state = state(&["######", "#_@b_#", "######"]);
+        assert_eq!(
+            state.board().reachable(state.player()),
+            HashSet::from([IVec2::new(1, 1), IVec2::new(2, 1)])
+        );
+    }
+
+    #[test]
+    fn won_once_no_block_can_move() {
+        let mut state = state(&["#####", "#@bO#", "#####"]);
+        assert!(!state.is_won());
+        state.step(Dir::Right);
+        assert!(state.is_won());
+
+        // A block in a corner can't be moved anymore
+        assert!(self::state(&["####", "#@b#", "####"]).is_won());
+    }
+
+    #[test]
+    fn path_around_walls() {
+        let mut state = state(&["#######", "#@____#", "#_#_#_#", "#_____#", "#######"]);
+        let target = IVec2::new(5, 1);
+        let path = state.board().path(state.player(), target).unwrap();
+        assert_eq!(path.len(), 6);
+        for dir in path {
+            assert_eq!(state.step(dir), moved(false, false, false));
+        }
+        assert_eq!(state.player(), target);
+    }
+
+    #[test]
+    fn path_to_own_tile_is_empty() {
+        let state = state(&["###", "#@#", "###"]);
+        assert_eq!(
+            state.board().path(state.player(), state.player()),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn path_never_moves_blocks() {
+        // Walking through the block would push it, walking away from the pullable pulls it
+        let state = state(&["#####", "#@b_#", "#####"]);
+        assert_eq!(state.board().path(state.player(), IVec2::new(3, 1)), None);
+        let state = self::state(&["#####", "#p@_#", "#####"]);
+        assert_eq!(state.board().path(state.player(), IVec2::new(3, 1)), None);
+    }
+}
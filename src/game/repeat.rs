@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/// How actions repeat while their key is held, shared by movement and undo/redo
+/// so that both feel the same.
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+pub struct RepeatPolicy {
+    /// Time between the press and the first repeat.
+    pub delay: Duration,
+    /// Time between the first and second repeat.
+    pub interval: Duration,
+    /// Shortest time between repeats the interval speeds up to.
+    pub min_interval: Duration,
+    /// Every repeat the interval is multiplied by this.
+    pub acceleration: f32,
+}
+
+impl Default for RepeatPolicy {
+    fn default() -> Self {
+        RepeatPolicy {
+            delay: Duration::from_millis(250),
+            interval: Duration::from_millis(200),
+            min_interval: Duration::from_millis(60),
+            acceleration: 0.85,
+        }
+    }
+}
+
+impl RepeatPolicy {
+    fn interval(&self, repeats: u32) -> Duration {
+        self.interval
+            .mul_f32(self.acceleration.powi(repeats as i32))
+            .max(self.min_interval)
+    }
+}
+
+/// Repeat state of a held action.
+#[derive(Reflect, Clone, Debug, Default)]
+pub struct KeyRepeat {
+    /// Time until the action fires again, `None` while it isn't held.
+    next: Option<Duration>,
+    repeats: u32,
+}
+
+impl KeyRepeat {
+    /// Returns whether the action fires this frame, it fires right away when pressed.
+    pub fn tick(&mut self, pressed: bool, delta: Duration, policy: &RepeatPolicy) -> bool {
+        if !pressed {
+            *self = KeyRepeat::default();
+            return false;
+        }
+
+        let Some(next) = self.next else {
+            self.next = Some(policy.delay);
+            return true;
+        };
+        if let Some(next) = next.checked_sub(delta).filter(|next| !next.is_zero()) {
+            self.next = Some(next);
+            return false;
+        }
+        // Carry over the overshoot so frame rate doesn't change the repeat rate
+        let overshoot = delta.saturating_sub(next);
+        self.next = Some(policy.interval(self.repeats).saturating_sub(overshoot));
+        self.repeats += 1;
+        true
+    }
+}
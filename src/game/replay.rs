@@ -0,0 +1,413 @@
+use std::time::Duration;
+
+use bevy::{log, prelude::*, time::Stopwatch};
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::cleanup::DependOnState;
+
+use super::{
+    history::{HandleHistoryEvents, HistoryEvent},
+    level::{LevelData, LevelId},
+    player::{player_movement, MoveRequest, PlayerInput},
+    Dir, GameState,
+};
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(InputManagerPlugin::<ReplayAction>::default())
+            .init_resource::<MoveLog>()
+            .init_resource::<SessionClock>()
+            .register_type::<MoveLog>()
+            .configure_sets(Update, PlayerInput.run_if(not(resource_exists::<Replay>)))
+            .add_systems(Startup, setup)
+            .add_systems(
+                OnTransition {
+                    exited: GameState::LevelTransition,
+                    entered: GameState::Play,
+                },
+                (start_session, start_replay, spawn_replay_ui).chain(),
+            )
+            .add_systems(OnExit(GameState::Play), stop_replay)
+            .add_systems(
+                Update,
+                (
+                    tick_clock,
+                    replay_steps
+                        .before(player_movement)
+                        .before(HandleHistoryEvents)
+                        .run_if(resource_exists::<Replay>),
+                    record_steps
+                        .after(PlayerInput)
+                        .run_if(not(resource_exists::<Replay>)),
+                    update_replay_ui,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Play)),
+            );
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_systems(
+            Update,
+            (
+                save_log
+                    .after(record_steps)
+                    .run_if(in_state(GameState::Play).or_else(in_state(GameState::Won))),
+                load_dropped_replay.run_if(not(in_state(GameState::AssetLoading))),
+            ),
+        );
+    }
+}
+
+#[derive(Actionlike, Clone, Copy, Hash, Debug, PartialEq, Eq, Reflect)]
+pub enum ReplayAction {
+    TogglePause,
+    Step,
+    /// Stops the replay and hands control back to the player.
+    Stop,
+    Save,
+}
+
+fn replay_actions() -> InputMap<ReplayAction> {
+    use ReplayAction::*;
+    let mut input_map = InputMap::default();
+
+    input_map.insert(TogglePause, KeyCode::Space);
+    input_map.insert(Step, KeyCode::Period);
+    input_map.insert(Stop, KeyCode::Escape);
+    input_map.insert(Save, KeyCode::F6);
+
+    input_map
+}
+
+fn setup(mut cmds: Commands) {
+    cmds.spawn((
+        (InputManagerBundle::<ReplayAction> {
+            input_map: replay_actions(),
+            ..default()
+        },),
+        Name::new("ReplayActions"),
+    ));
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum Step {
+    Move(Dir),
+    Undo,
+    Redo,
+    Reset,
+}
+
+/// A step and when it happened, in milliseconds since the level started.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub struct LoggedStep(pub u64, pub Step);
+
+/// Everything the player did in the current attempt at a level.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default, Reflect)]
+#[reflect(Resource)]
+pub struct MoveLog {
+    pub pack: String,
+    pub level: LevelId,
+    /// Layout the log was recorded on, see [`Level::fingerprint`](super::level::Level::fingerprint).
+    pub fingerprint: u64,
+    pub steps: Vec<LoggedStep>,
+}
+
+/// Time spent in the current attempt at a level, stands still while a replay is paused.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct SessionClock(pub Stopwatch);
+
+/// A [`MoveLog`] that is played back instead of taking input from the player.
+#[derive(Resource, Debug)]
+pub struct Replay {
+    pub log: MoveLog,
+    /// Index of the next step to play.
+    pub next: usize,
+    pub paused: bool,
+}
+
+/// Replay that starts once its level is entered.
+#[derive(Resource, Debug)]
+pub struct PendingReplay(pub MoveLog);
+
+fn start_session(
+    mut move_log: ResMut<MoveLog>,
+    mut clock: ResMut<SessionClock>,
+    level_data: LevelData,
+) {
+    *move_log = MoveLog {
+        pack: level_data.current_pack().id.clone(),
+        level: level_data.current_level().clone(),
+        fingerprint: level_data.current_level_data().fingerprint(),
+        steps: Vec::new(),
+    };
+    clock.reset();
+}
+
+fn start_replay(mut cmds: Commands, pending: Option<ResMut<PendingReplay>>) {
+    let Some(mut pending) = pending else {
+        return;
+    };
+    cmds.remove_resource::<PendingReplay>();
+    cmds.insert_resource(Replay {
+        log: std::mem::take(&mut pending.0),
+        next: 0,
+        paused: false,
+    });
+}
+
+fn stop_replay(mut cmds: Commands) {
+    cmds.remove_resource::<Replay>();
+}
+
+fn tick_clock(mut clock: ResMut<SessionClock>, replay: Option<Res<Replay>>, time: Res<Time>) {
+    if replay.is_some_and(|replay| replay.paused) {
+        return;
+    }
+    clock.tick(time.delta());
+}
+
+fn record_steps(
+    mut move_log: ResMut<MoveLog>,
+    mut move_requests: EventReader<MoveRequest>,
+    mut history_events: EventReader<HistoryEvent>,
+    clock: Res<SessionClock>,
+) {
+    let millis = clock.elapsed().as_millis() as u64;
+    // Undo, redo and reset of a frame are handled before its move, replays play one step
+    // per frame in the order they are logged
+    for ev in history_events.read() {
+        let step = match ev {
+            HistoryEvent::Rewind => Step::Undo,
+            HistoryEvent::Redo => Step::Redo,
+            HistoryEvent::Reset => Step::Reset,
+            HistoryEvent::Record => continue,
+        };
+        move_log.steps.push(LoggedStep(millis, step));
+    }
+    for MoveRequest(dir) in move_requests.read() {
+        move_log.steps.push(LoggedStep(millis, Step::Move(*dir)));
+    }
+}
+
+/// Ends the replay, the steps played so far become the start of the player's own log.
+fn hand_over(cmds: &mut Commands, replay: &Replay, move_log: &mut MoveLog) {
+    move_log.steps = replay.log.steps[..replay.next].to_vec();
+    cmds.remove_resource::<Replay>();
+}
+
+fn replay_steps(
+    mut cmds: Commands,
+    mut replay: ResMut<Replay>,
+    mut move_log: ResMut<MoveLog>,
+    mut clock: ResMut<SessionClock>,
+    mut move_requests: EventWriter<MoveRequest>,
+    mut history_events: EventWriter<HistoryEvent>,
+    actions: Query<&ActionState<ReplayAction>>,
+) {
+    let Ok(actions) = actions.get_single() else {
+        return;
+    };
+    if actions.just_pressed(&ReplayAction::Stop) {
+        log::info!("Stopped replay after {} steps", replay.next);
+        hand_over(&mut cmds, &replay, &mut move_log);
+        return;
+    }
+    if actions.just_pressed(&ReplayAction::TogglePause) {
+        replay.paused = !replay.paused;
+    }
+    let step_once = replay.paused && actions.just_pressed(&ReplayAction::Step);
+
+    // At most one step per frame, the same as when playing
+    let Some(LoggedStep(millis, step)) = replay.log.steps.get(replay.next).copied() else {
+        log::info!("Replay finished");
+        hand_over(&mut cmds, &replay, &mut move_log);
+        return;
+    };
+    let due = Duration::from_millis(millis);
+    if step_once {
+        clock.set_elapsed(due);
+    } else if replay.paused || clock.elapsed() < due {
+        return;
+    }
+
+    match step {
+        Step::Move(dir) => {
+            move_requests.send(MoveRequest(dir));
+        }
+        Step::Undo => {
+            history_events.send(HistoryEvent::Rewind);
+        }
+        Step::Redo => {
+            history_events.send(HistoryEvent::Redo);
+        }
+        Step::Reset => {
+            history_events.send(HistoryEvent::Reset);
+        }
+    }
+    replay.next += 1;
+}
+
+#[derive(Component)]
+struct ReplayText;
+
+fn spawn_replay_ui(mut cmds: Commands, replay: Option<Res<Replay>>) {
+    if replay.is_none() {
+        return;
+    }
+    cmds.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            right: Val::Px(10.),
+            ..default()
+        }),
+        ReplayText,
+        DependOnState::single(GameState::Play),
+        Name::new("Replay UI"),
+    ));
+}
+
+fn update_replay_ui(
+    mut cmds: Commands,
+    replay: Option<Res<Replay>>,
+    mut text: Query<(Entity, &mut Text), With<ReplayText>>,
+) {
+    let Ok((entity, mut text)) = text.get_single_mut() else {
+        return;
+    };
+    let Some(replay) = replay else {
+        cmds.entity(entity).despawn_recursive();
+        return;
+    };
+    text.sections[0].value = format!(
+        "Replay {}/{}{}  [Space] Pause  [.] Step  [Esc] Take over",
+        replay.next,
+        replay.log.steps.len(),
+        if replay.paused { " (paused)" } else { "" },
+    );
+}
+
+/// Where move logs are written to, next to the save file.
+#[cfg(not(target_arch = "wasm32"))]
+fn replay_dir(save_path: &super::save::SavePath) -> Option<std::path::PathBuf> {
+    Some(save_path.0.as_ref()?.parent()?.join("replays"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_log(
+    move_log: Res<MoveLog>,
+    actions: Query<&ActionState<ReplayAction>>,
+    mut level_completed: EventReader<super::win::LevelCompleted>,
+    replay: Option<Res<Replay>>,
+    save_path: Res<super::save::SavePath>,
+) {
+    let Ok(actions) = actions.get_single() else {
+        return;
+    };
+    // Logs of completed levels are kept automatically
+    let completed = level_completed.read().filter(|ev| !ev.replayed).count() > 0;
+    if replay.is_some() || !(completed || actions.just_pressed(&ReplayAction::Save)) {
+        return;
+    }
+    let Some(dir) = replay_dir(&save_path) else {
+        log::warn!("Replays can't be saved without a save location");
+        return;
+    };
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let file_name = format!(
+        "{}-{}-{}.replay.ron",
+        move_log.pack, move_log.level, timestamp
+    )
+    .replace(
+        |c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '.'),
+        "_",
+    );
+    let file = dir.join(file_name);
+    let result = ron::ser::to_string_pretty(&*move_log, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())
+        .and_then(|contents| {
+            std::fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+            std::fs::write(&file, contents).map_err(|err| err.to_string())
+        });
+    match result {
+        Ok(()) => log::info!("Saved replay to {}", file.display()),
+        Err(err) => log::error!("Could not save replay to {}: {}", file.display(), err),
+    }
+}
+
+/// Starts the replay of a `*.replay.ron` file dropped onto the window.
+#[cfg(not(target_arch = "wasm32"))]
+fn load_dropped_replay(
+    mut cmds: Commands,
+    mut drops: EventReader<FileDragAndDrop>,
+    mut current_pack: ResMut<super::pack::CurrentPack>,
+    mut current_level: ResMut<super::level_select::CurrentLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+    packs: Res<Assets<super::pack::LevelPacks>>,
+    levels: Res<Assets<super::level::Levels>>,
+    assets: Res<super::GameAssets>,
+) {
+    for ev in drops.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = ev else {
+            continue;
+        };
+        if !path_buf.to_string_lossy().ends_with(".replay.ron") {
+            continue;
+        }
+        let move_log = match std::fs::read_to_string(path_buf)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| ron::from_str::<MoveLog>(&contents).map_err(|err| err.to_string()))
+        {
+            Ok(move_log) => move_log,
+            Err(err) => {
+                log::error!("Could not read replay {}: {}", path_buf.display(), err);
+                continue;
+            }
+        };
+
+        let Some(pack) = packs
+            .get(&assets.packs)
+            .and_then(|packs| packs.get_by_id(&move_log.pack))
+        else {
+            log::error!("Replay is for pack {} which isn't listed", move_log.pack);
+            continue;
+        };
+        let Some(level) = levels
+            .get(&pack.levels)
+            .and_then(|levels| levels.get_by_id(&move_log.level))
+        else {
+            log::error!("Replay is for level {} which doesn't exist", move_log.level);
+            continue;
+        };
+        if level.fingerprint() != move_log.fingerprint {
+            log::warn!(
+                "Level {} changed since the replay was recorded",
+                move_log.level
+            );
+        }
+
+        log::info!(
+            "Replaying {} steps of level {}",
+            move_log.steps.len(),
+            move_log.level
+        );
+        *current_pack = pack.into();
+        current_level.0 = move_log.level.clone();
+        cmds.insert_resource(PendingReplay(move_log));
+        next_state.set(GameState::LevelTransition);
+    }
+}
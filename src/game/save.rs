@@ -0,0 +1,457 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use bevy::{log, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    level::{Level, LevelData, LevelId, Levels},
+    level_select::CurrentLevel,
+    pack::{CurrentPack, LevelPacks},
+    sound::SoundSettings,
+    win::LevelCompleted,
+    GameAssets, GameState,
+};
+
+/// Bump when the layout of [`SaveData`] changes in a way `#[serde(default)]` can't cover.
+pub const SAVE_VERSION: u32 = 3;
+
+/// Overrides where the save file is stored.
+pub const SAVE_PATH_VAR: &str = "LOST_SAVE_PATH";
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SavePath>()
+            .register_type::<SaveData>()
+            .add_systems(PreStartup, load_save)
+            .add_systems(OnExit(GameState::AssetLoading), migrate_save)
+            .add_systems(
+                OnTransition {
+                    exited: GameState::LevelTransition,
+                    entered: GameState::Play,
+                },
+                record_last_played,
+            )
+            .add_systems(
+                Update,
+                record_completion.run_if(on_event::<LevelCompleted>()),
+            );
+    }
+}
+
+/// Location of the save file, `None` disables saving.
+#[derive(Resource, Debug, Clone)]
+pub struct SavePath(pub Option<PathBuf>);
+
+impl Default for SavePath {
+    fn default() -> Self {
+        let path = std::env::var_os(SAVE_PATH_VAR)
+            .map(PathBuf::from)
+            .or_else(|| data_dir().map(|dir| dir.join("lost").join("save.ron")));
+        SavePath(path)
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn data_dir() -> Option<PathBuf> {
+    std::env::var_os("APPDATA").map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+pub fn data_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn data_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+pub fn data_dir() -> Option<PathBuf> {
+    None
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Reflect)]
+#[serde(default)]
+pub struct LevelProgress {
+    /// Identifies the layout the progress was made on, see [`Level::fingerprint`](super::level::Level::fingerprint).
+    pub fingerprint: u64,
+    pub completed: bool,
+    pub best_moves: Option<usize>,
+    pub best_undos: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Reflect)]
+#[serde(default)]
+pub struct PackProgress {
+    /// Progress keyed by level id. Version 1 used level indices, which are read
+    /// as the ids of levels without an explicit id.
+    pub levels: BTreeMap<LevelId, LevelProgress>,
+    pub last_played: Option<LevelId>,
+    /// Layout of the last played level, so it is found again like progress is.
+    pub last_played_fingerprint: Option<u64>,
+}
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct SaveData {
+    pub version: u32,
+    /// Progress keyed by pack id, see [`LevelPack::id`](super::pack::LevelPack::id).
+    pub packs: BTreeMap<String, PackProgress>,
+    pub last_pack: Option<String>,
+    pub sound: SoundSettings,
+    /// Before version 3 there was only a single pack.
+    #[serde(rename = "levels", skip_serializing)]
+    legacy_levels: BTreeMap<LevelId, LevelProgress>,
+    #[serde(rename = "last_played", skip_serializing)]
+    legacy_last_played: Option<LevelId>,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        Self {
+            version: SAVE_VERSION,
+            packs: BTreeMap::new(),
+            last_pack: None,
+            sound: SoundSettings::default(),
+            legacy_levels: BTreeMap::new(),
+            legacy_last_played: None,
+        }
+    }
+}
+
+impl PackProgress {
+    pub fn progress(&self, level: &LevelId) -> Option<&LevelProgress> {
+        self.levels.get(level)
+    }
+
+    pub fn is_completed(&self, level: &LevelId) -> bool {
+        self.progress(level)
+            .is_some_and(|progress| progress.completed)
+    }
+
+    pub fn record_completion(
+        &mut self,
+        level: &LevelId,
+        fingerprint: u64,
+        moves: usize,
+        undos: usize,
+    ) {
+        let progress = self.levels.entry(level.clone()).or_default();
+        progress.fingerprint = fingerprint;
+        progress.completed = true;
+        progress.best_moves = Some(progress.best_moves.map_or(moves, |best| best.min(moves)));
+        progress.best_undos = Some(progress.best_undos.map_or(undos, |best| best.min(undos)));
+    }
+
+    /// Reassigns progress to levels after levels were inserted, removed or reordered.
+    ///
+    /// Progress of a level with an explicit id always stays with that level. Other progress
+    /// follows the level with the same layout, and if that can't be found anymore it stays
+    /// at its index, assuming the level was edited in place.
+    pub fn migrate(&mut self, levels: &Levels) {
+        let mut migrated = BTreeMap::new();
+        let mut unmatched = Vec::new();
+        // Saves from before the fingerprint was stored only know it from the level's progress
+        let last_played_fingerprint = self.last_played_fingerprint.or_else(|| {
+            let id = self.last_played.as_ref()?;
+            self.levels.get(id).map(|progress| progress.fingerprint)
+        });
+
+        for (id, progress) in std::mem::take(&mut self.levels) {
+            let explicit = levels
+                .get_by_id(&id)
+                .is_some_and(|level| level.meta.id.is_some());
+            if explicit {
+                migrated.insert(id, progress);
+            } else {
+                unmatched.push((id, progress));
+            }
+        }
+
+        let mut orphaned = Vec::new();
+        for (id, progress) in unmatched {
+            let matching = levels.iter().find(|level| {
+                level.fingerprint() == progress.fingerprint && !migrated.contains_key(&level.id)
+            });
+            match matching {
+                Some(level) => {
+                    if level.id != id {
+                        log::info!("Moved progress of level {} to {}", id, level.id);
+                    }
+                    migrated.insert(level.id.clone(), progress);
+                }
+                None => orphaned.push((id, progress)),
+            }
+        }
+        for (id, mut progress) in orphaned {
+            match levels.get_by_id(&id) {
+                Some(level) if !migrated.contains_key(&id) => {
+                    progress.fingerprint = level.fingerprint();
+                    migrated.insert(id, progress);
+                }
+                _ => log::info!("Dropped progress of level {} that no longer exists", id),
+            }
+        }
+
+        self.levels = migrated;
+        // The last played level is looked up the same way, but on its own as it may have no progress
+        let last_played = self.last_played.take().and_then(|id| {
+            let level = levels.get_by_id(&id);
+            let keep = level.is_some_and(|level| {
+                level.meta.id.is_some() || Some(level.fingerprint()) == last_played_fingerprint
+            });
+            let matching = last_played_fingerprint
+                .filter(|_| !keep)
+                .and_then(|fingerprint| {
+                    levels
+                        .iter()
+                        .find(|level| level.fingerprint() == fingerprint)
+                });
+            if let Some(matching) = matching.filter(|matching| matching.id != id) {
+                log::info!("Moved last played level {} to {}", id, matching.id);
+            }
+            matching.or(level)
+        });
+        self.last_played = last_played.map(|level| level.id.clone());
+        self.last_played_fingerprint = last_played.map(Level::fingerprint);
+    }
+}
+
+impl SaveData {
+    pub fn pack(&self, pack: &str) -> Option<&PackProgress> {
+        self.packs.get(pack)
+    }
+
+    pub fn pack_mut(&mut self, pack: &str) -> &mut PackProgress {
+        self.packs.entry(pack.to_string()).or_default()
+    }
+
+    pub fn is_completed(&self, pack: &str, level: &LevelId) -> bool {
+        self.pack(pack)
+            .is_some_and(|progress| progress.is_completed(level))
+    }
+
+    /// Moves progress of older save versions into the current layout and reassigns
+    /// progress within every pack, see [`PackProgress::migrate`].
+    ///
+    /// Progress of packs that aren't listed anymore is kept in case they come back.
+    pub fn migrate(&mut self, packs: &LevelPacks, levels: &Assets<Levels>) {
+        if self.version < 3 {
+            if let Some(first) = packs.first() {
+                let legacy_levels = std::mem::take(&mut self.legacy_levels);
+                let legacy_last_played = self.legacy_last_played.take();
+                let progress = self.pack_mut(&first.id);
+                progress.levels.extend(legacy_levels);
+                progress.last_played = progress.last_played.take().or(legacy_last_played);
+                self.last_pack.get_or_insert_with(|| first.id.clone());
+            }
+        }
+
+        for pack in packs.iter() {
+            if let Some(levels) = levels.get(&pack.levels) {
+                self.pack_mut(&pack.id).migrate(levels);
+            }
+        }
+        self.last_pack = self
+            .last_pack
+            .take()
+            .filter(|id| packs.get_by_id(id).is_some());
+        self.version = SAVE_VERSION;
+    }
+
+    pub fn load(path: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        match ron::from_str::<SaveData>(&contents) {
+            Ok(save) => {
+                if save.version > SAVE_VERSION {
+                    log::warn!(
+                        "Save file version {} is newer than supported version {}",
+                        save.version,
+                        SAVE_VERSION
+                    );
+                }
+                Some(save)
+            }
+            Err(err) => {
+                // Keep the broken file around instead of overwriting it on the next save
+                let backup = path.with_extension("ron.bak");
+                log::error!(
+                    "Could not parse save file {}, moving it to {}: {}",
+                    path.display(),
+                    backup.display(),
+                    err
+                );
+                let _ = std::fs::rename(path, backup);
+                None
+            }
+        }
+    }
+
+    pub fn write(&self, path: &Path) {
+        let result = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|contents| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+                }
+                std::fs::write(path, contents).map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            log::error!("Could not write save file {}: {}", path.display(), err);
+        }
+    }
+}
+
+fn load_save(mut cmds: Commands, save_path: Res<SavePath>) {
+    let save = save_path
+        .0
+        .as_ref()
+        .and_then(|path| SaveData::load(path))
+        .unwrap_or_default();
+    cmds.insert_resource(save);
+}
+
+fn migrate_save(
+    mut save: ResMut<SaveData>,
+    mut current_pack: ResMut<CurrentPack>,
+    mut current_level: ResMut<CurrentLevel>,
+    levels: Res<Assets<Levels>>,
+    packs: Res<Assets<LevelPacks>>,
+    assets: Res<GameAssets>,
+    save_path: Res<SavePath>,
+) {
+    let packs = packs
+        .get(&assets.packs)
+        .expect("Pack index should be loaded");
+    save.migrate(packs, &levels);
+
+    let pack = save
+        .last_pack
+        .as_deref()
+        .and_then(|id| packs.get_by_id(id))
+        .or(packs.first())
+        .expect("Pack index lists at least one pack");
+    *current_pack = pack.into();
+    if let Some(last_played) = save
+        .pack(&pack.id)
+        .and_then(|progress| progress.last_played.clone())
+    {
+        **current_level = last_played;
+    }
+    if let Some(path) = save_path.0.as_ref() {
+        save.write(path);
+    }
+}
+
+fn record_last_played(mut save: ResMut<SaveData>, level_data: LevelData, save_path: Res<SavePath>) {
+    let pack = &level_data.current_pack().id;
+    let level = level_data.current_level();
+    let fingerprint = level_data.current_level_data().fingerprint();
+    if save.last_pack.as_ref() == Some(pack)
+        && save.pack(pack).is_some_and(|progress| {
+            progress.last_played.as_ref() == Some(level)
+                && progress.last_played_fingerprint == Some(fingerprint)
+        })
+    {
+        return;
+    }
+    save.last_pack = Some(pack.clone());
+    let progress = save.pack_mut(pack);
+    progress.last_played = Some(level.clone());
+    progress.last_played_fingerprint = Some(fingerprint);
+    if let Some(path) = save_path.0.as_ref() {
+        save.write(path);
+    }
+}
+
+fn record_completion(
+    mut level_completed: EventReader<LevelCompleted>,
+    mut save: ResMut<SaveData>,
+    level_data: LevelData,
+    save_path: Res<SavePath>,
+) {
+    for ev in level_completed.read() {
+        if ev.replayed {
+            continue;
+        }
+        let Some(level) = level_data.levels().get_by_id(&ev.level) else {
+            continue;
+        };
+        save.pack_mut(&level_data.current_pack().id)
+            .record_completion(&ev.level, level.fingerprint(), ev.moves, ev.undos);
+    }
+    if let Some(path) = save_path.0.as_ref() {
+        save.write(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::puzzle::test_level;
+
+    const SMALL: &[&str] = &["###", "#@#", "###"];
+    const WIDE: &[&str] = &["####", "#@_#", "####"];
+
+    /// Levels without explicit ids, like most of the levels files.
+    fn levels(layouts: &[&[&str]]) -> Levels {
+        Levels(
+            layouts
+                .iter()
+                .enumerate()
+                .map(|(index, rows)| Level {
+                    id: LevelId::from_index(index),
+                    ..test_level(rows)
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn last_played_follows_reordered_level() {
+        let old = levels(&[SMALL, WIDE]);
+        let mut progress = PackProgress {
+            last_played: Some(old[1].id.clone()),
+            last_played_fingerprint: Some(old[1].fingerprint()),
+            ..Default::default()
+        };
+        progress.migrate(&levels(&[WIDE, SMALL]));
+        assert_eq!(progress.last_played, Some(LevelId::from_index(0)));
+        assert!(progress.levels.is_empty());
+    }
+
+    #[test]
+    fn last_played_uses_progress_of_older_saves() {
+        let old = levels(&[SMALL, WIDE]);
+        let mut progress = PackProgress {
+            last_played: Some(old[1].id.clone()),
+            ..Default::default()
+        };
+        progress.record_completion(&old[1].id, old[1].fingerprint(), 3, 0);
+        progress.migrate(&levels(&[WIDE, SMALL]));
+        assert_eq!(progress.last_played, Some(LevelId::from_index(0)));
+        assert!(progress.is_completed(&LevelId::from_index(0)));
+    }
+
+    #[test]
+    fn last_played_edited_in_place_stays() {
+        let mut progress = PackProgress {
+            last_played: Some(LevelId::from_index(1)),
+            last_played_fingerprint: Some(0),
+            ..Default::default()
+        };
+        let new = levels(&[SMALL, WIDE]);
+        progress.migrate(&new);
+        assert_eq!(progress.last_played, Some(LevelId::from_index(1)));
+        assert_eq!(progress.last_played_fingerprint, Some(new[1].fingerprint()));
+    }
+}
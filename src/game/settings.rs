@@ -0,0 +1,302 @@
+use bevy::prelude::*;
+
+use crate::{cleanup::DependOnState, ui::NineSliceButtonText};
+
+use super::{
+    bindings::{Binding, Bindings, BoundAction, ConfigPath},
+    Dir, GameAssets, GameState,
+};
+
+const CURRENT_STATE: GameState = GameState::Settings;
+
+/// How far the stick has to be pushed to be bound.
+const STICK_THRESHOLD: f32 = 0.5;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_systems(OnEnter(CURRENT_STATE), (stop_rebinding, spawn_settings))
+            .add_systems(
+                Update,
+                (handle_buttons, capture_binding, update_binding_buttons)
+                    .chain()
+                    .run_if(in_state(CURRENT_STATE)),
+            );
+    }
+}
+
+/// Binding that the next key or gamepad button that is pressed is bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RebindTarget {
+    pub action: BoundAction,
+    /// Binding that is replaced, the new one is added next to the others if there is none.
+    pub replacing: Option<Binding>,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct Rebinding(pub Option<RebindTarget>);
+
+#[derive(Component, Clone)]
+enum SettingsButton {
+    Replace(BoundAction, Binding),
+    Add(BoundAction),
+    /// Stands in for the binding that is being rebound.
+    Waiting,
+    Defaults,
+    Back,
+}
+
+impl From<SettingsButton> for String {
+    fn from(value: SettingsButton) -> Self {
+        match value {
+            SettingsButton::Replace(_, binding) => binding.to_string(),
+            SettingsButton::Add(_) => "Add".to_string(),
+            SettingsButton::Waiting => "Press a key or button...".to_string(),
+            SettingsButton::Defaults => "Defaults".to_string(),
+            SettingsButton::Back => "Back".to_string(),
+        }
+    }
+}
+
+/// Holds a button for every binding of the action.
+#[derive(Component)]
+struct BindingButtons(BoundAction);
+
+fn small_button() -> Style {
+    Style {
+        min_width: Val::Px(100.0),
+        height: Val::Px(32.0),
+        margin: UiRect::all(Val::Px(2.)),
+        padding: UiRect::horizontal(Val::Px(8.)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        border: UiRect::all(Val::Px(2.)),
+        ..default()
+    }
+}
+
+fn stop_rebinding(mut rebinding: ResMut<Rebinding>) {
+    rebinding.0 = None;
+}
+
+fn spawn_settings(mut cmds: Commands, bindings: Res<Bindings>, assets: Res<GameAssets>) {
+    let text_style = TextStyle {
+        font_size: 20.,
+        color: Color::WHITE,
+        ..default()
+    };
+    let column = cmds
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    margin: UiRect::all(Val::Auto),
+                    ..default()
+                },
+                ..default()
+            },
+            DependOnState::single(CURRENT_STATE),
+            Name::new("Settings"),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Pick a binding to replace or add one, then press a key or gamepad button, \
+                 escape cancels",
+                text_style.clone(),
+            ));
+        })
+        .id();
+
+    for action in bindings.actions() {
+        let row = cmds
+            .spawn(NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn(
+                    TextBundle::from_section(action.to_string(), text_style.clone()).with_style(
+                        Style {
+                            width: Val::Px(180.),
+                            ..default()
+                        },
+                    ),
+                );
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(560.),
+                            flex_wrap: FlexWrap::Wrap,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    BindingButtons(action),
+                ));
+            })
+            .id();
+        cmds.entity(column).add_child(row);
+    }
+
+    let button_row = cmds.spawn(NodeBundle::default()).id();
+    cmds.entity(column).add_child(button_row);
+    for button in [SettingsButton::Defaults, SettingsButton::Back] {
+        cmds.add(NineSliceButtonText {
+            button,
+            style: Style {
+                width: Val::Px(200.0),
+                height: Val::Px(60.0),
+                margin: UiRect::all(Val::Px(10.)),
+                ..small_button()
+            },
+            texture: assets.button.clone_weak(),
+            parent: button_row,
+            disabled: false,
+        });
+    }
+}
+
+fn handle_buttons(
+    mut game_state: ResMut<NextState<GameState>>,
+    buttons: Query<(&SettingsButton, &Interaction), Changed<Interaction>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<Bindings>,
+    config_path: Res<ConfigPath>,
+) {
+    for (button, interaction) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            SettingsButton::Replace(action, binding) => {
+                rebinding.0 = Some(RebindTarget {
+                    action: *action,
+                    replacing: Some(*binding),
+                })
+            }
+            SettingsButton::Add(action) => {
+                rebinding.0 = Some(RebindTarget {
+                    action: *action,
+                    replacing: None,
+                })
+            }
+            SettingsButton::Waiting => {}
+            SettingsButton::Defaults => {
+                rebinding.0 = None;
+                *bindings = Bindings::default();
+                if let Some(path) = config_path.0.as_ref() {
+                    bindings.write(path);
+                }
+            }
+            SettingsButton::Back => game_state.set(GameState::PackSelect),
+        }
+    }
+}
+
+/// First key, gamepad button or stick direction pressed this frame.
+fn pressed_binding(
+    keys: &ButtonInput<KeyCode>,
+    gamepad_buttons: &ButtonInput<GamepadButton>,
+    gamepads: &Gamepads,
+    axes: &Axis<GamepadAxis>,
+) -> Option<Binding> {
+    if let Some(key) = keys.get_just_pressed().next() {
+        return Some(Binding::Key(*key));
+    }
+    if let Some(button) = gamepad_buttons.get_just_pressed().next() {
+        return Some(Binding::Button(button.button_type));
+    }
+    gamepads.iter().find_map(|gamepad| {
+        let axis = |axis_type| axes.get(GamepadAxis::new(gamepad, axis_type)).unwrap_or(0.);
+        let (x, y) = (
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        );
+        if x.abs().max(y.abs()) < STICK_THRESHOLD {
+            None
+        } else if x.abs() > y.abs() {
+            Some(Binding::Stick(if x > 0. { Dir::Right } else { Dir::Left }))
+        } else {
+            Some(Binding::Stick(if y > 0. { Dir::Up } else { Dir::Down }))
+        }
+    })
+}
+
+fn capture_binding(
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<Bindings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    config_path: Res<ConfigPath>,
+) {
+    let Some(target) = rebinding.0 else {
+        return;
+    };
+    let Some(binding) = pressed_binding(&keys, &gamepad_buttons, &gamepads, &axes) else {
+        return;
+    };
+
+    rebinding.0 = None;
+    if binding == Binding::Key(KeyCode::Escape) {
+        return;
+    }
+    bindings.rebind(target.action, target.replacing, binding);
+    if let Some(path) = config_path.0.as_ref() {
+        bindings.write(path);
+    }
+}
+
+/// Respawns the buttons of each action once they are spawned and whenever they change.
+fn update_binding_buttons(
+    mut cmds: Commands,
+    rows: Query<(Entity, Ref<BindingButtons>)>,
+    bindings: Res<Bindings>,
+    rebinding: Res<Rebinding>,
+    assets: Res<GameAssets>,
+) {
+    for (row, binding_buttons) in rows.iter() {
+        if !binding_buttons.is_added() && !bindings.is_changed() && !rebinding.is_changed() {
+            continue;
+        }
+        let action = binding_buttons.0;
+        let target = rebinding.0.filter(|target| target.action == action);
+
+        let mut buttons = bindings
+            .get(action)
+            .iter()
+            .map(|binding| {
+                if target.is_some_and(|target| target.replacing == Some(*binding)) {
+                    SettingsButton::Waiting
+                } else {
+                    SettingsButton::Replace(action, *binding)
+                }
+            })
+            .collect::<Vec<_>>();
+        buttons.push(if target.is_some_and(|target| target.replacing.is_none()) {
+            SettingsButton::Waiting
+        } else {
+            SettingsButton::Add(action)
+        });
+
+        cmds.entity(row).despawn_descendants();
+        for button in buttons {
+            let disabled = matches!(button, SettingsButton::Waiting);
+            cmds.add(NineSliceButtonText {
+                button,
+                style: small_button(),
+                texture: assets.button.clone_weak(),
+                parent: row,
+                disabled,
+            });
+        }
+    }
+}
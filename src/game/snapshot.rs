@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy_simple_tilemap::TileMap;
+
+use super::{
+    collision::init_collision_map,
+    history::{CurrentTime, HistoryBundle, HistoryEvent, HistoryMode},
+    level::{GroundTiles, LevelData, LevelRoot, TileKind},
+    mechanics::{despawn_on_pit, set_pit_tile, BlockId, Pit},
+    player::Player,
+    tween::FromPit,
+    EntityKind, GameState, TilePos,
+};
+
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Snapshots>()
+            .add_systems(
+                OnTransition {
+                    exited: GameState::LevelTransition,
+                    entered: GameState::Play,
+                },
+                start_snapshots
+                    .after(init_collision_map)
+                    .run_if(resource_equals(HistoryMode::Snapshots)),
+            )
+            .add_systems(
+                Update,
+                handle_history_events
+                    .after(despawn_on_pit)
+                    .run_if(resource_equals(HistoryMode::Snapshots))
+                    .run_if(in_state(GameState::Play)),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockState {
+    pub pos: TilePos,
+    pub kind: EntityKind,
+    /// Fell into the pit at `pos` and fills it.
+    pub in_pit: bool,
+}
+
+/// State of the board after a turn.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub player: TilePos,
+    /// Indexed by [`BlockId`], shared with the previous turn if no block moved.
+    pub blocks: Arc<[BlockState]>,
+}
+
+/// Every turn of the current level, used instead of per-entity history with
+/// [`HistoryMode::Snapshots`].
+///
+/// Turns after the current time can still be redone until the next move replaces them.
+#[derive(Resource, Default, Debug)]
+pub struct Snapshots {
+    pub turns: Vec<Snapshot>,
+    /// Positions of all pits at the start of the level.
+    pub pits: Vec<TilePos>,
+    /// Follows [`CurrentTime`] event by event, which is only updated once per frame.
+    time: CurrentTime,
+}
+
+fn start_snapshots(
+    mut snapshots: ResMut<Snapshots>,
+    level_data: LevelData,
+    player: Query<&TilePos, With<Player>>,
+    blocks: Query<(&BlockId, &TilePos, &EntityKind), Without<Player>>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let level = level_data.current_level_data();
+    let width = level.size.x as usize;
+    let pits = level
+        .tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, tile)| matches!(tile, TileKind::Pit))
+        .map(|(idx, _)| TilePos::new((idx % width) as i32, (idx / width) as i32))
+        .collect();
+
+    // Snapshots index blocks by their id
+    let mut initial = blocks.iter().collect::<Vec<_>>();
+    initial.sort_by_key(|(id, _, _)| id.0);
+    let initial = initial
+        .into_iter()
+        .map(|(_, pos, kind)| BlockState {
+            pos: *pos,
+            kind: *kind,
+            in_pit: false,
+        })
+        .collect::<Vec<_>>();
+
+    *snapshots = Snapshots {
+        turns: vec![Snapshot {
+            player: *player,
+            blocks: initial.into(),
+        }],
+        pits,
+        time: CurrentTime::default(),
+    };
+}
+
+fn capture(
+    previous: &Snapshot,
+    player: TilePos,
+    blocks: &Query<(Entity, &BlockId, &mut TilePos), Without<Player>>,
+) -> Snapshot {
+    let mut states = previous.blocks.to_vec();
+    for state in states.iter_mut() {
+        state.in_pit = true;
+    }
+    for (_, id, pos) in blocks.iter() {
+        states[id.0].pos = *pos;
+        states[id.0].in_pit = false;
+    }
+
+    let blocks = if *states == *previous.blocks {
+        previous.blocks.clone()
+    } else {
+        states.into()
+    };
+    Snapshot { player, blocks }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn restore(
+    cmds: &mut Commands,
+    snapshot: &Snapshot,
+    shown: Option<&Snapshot>,
+    pits: &[TilePos],
+    player: &mut Mut<TilePos>,
+    blocks: &mut Query<(Entity, &BlockId, &mut TilePos), Without<Player>>,
+    pit_entities: &Query<(Entity, &TilePos), (With<Pit>, Without<Player>, Without<BlockId>)>,
+    level_root: Entity,
+    tilemap: &mut TileMap,
+) {
+    player.set_if_neq(snapshot.player);
+
+    let mut present = vec![false; snapshot.blocks.len()];
+    for (entity, id, mut pos) in blocks.iter_mut() {
+        let state = snapshot.blocks[id.0];
+        present[id.0] = true;
+        if state.in_pit {
+            cmds.entity(entity).despawn_recursive();
+        } else {
+            pos.set_if_neq(state.pos);
+        }
+    }
+    for (idx, state) in snapshot.blocks.iter().enumerate() {
+        if !present[idx] && !state.in_pit {
+            cmds.entity(level_root).with_children(|parent| {
+                let mut block = parent.spawn((
+                    state.pos,
+                    state.kind,
+                    HistoryBundle::<TilePos>::default(),
+                    BlockId(idx),
+                ));
+                // Without the shown turn the block appears where it is
+                if let Some(shown) = shown {
+                    block.insert(FromPit(shown.blocks[idx].pos));
+                }
+            });
+        }
+    }
+
+    for pit in pits {
+        let filled = snapshot
+            .blocks
+            .iter()
+            .any(|state| state.in_pit && state.pos == *pit);
+        let pit_entity = pit_entities.iter().find(|(_, pos)| *pos == pit);
+        match (filled, pit_entity) {
+            (true, Some((entity, _))) => {
+                cmds.entity(entity).despawn_recursive();
+                set_pit_tile(tilemap, *pit, true);
+            }
+            (false, None) => {
+                cmds.entity(level_root).with_children(|parent| {
+                    parent.spawn((*pit, EntityKind::Pit));
+                });
+                set_pit_tile(tilemap, *pit, false);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn handle_history_events(
+    mut cmds: Commands,
+    mut snapshots: ResMut<Snapshots>,
+    mut history_events: EventReader<HistoryEvent>,
+    mut player: Query<&mut TilePos, With<Player>>,
+    mut blocks: Query<(Entity, &BlockId, &mut TilePos), Without<Player>>,
+    pit_entities: Query<(Entity, &TilePos), (With<Pit>, Without<Player>, Without<BlockId>)>,
+    level_root: Query<Entity, With<LevelRoot>>,
+    mut tilemap: Query<&mut TileMap, With<GroundTiles>>,
+) {
+    let (Ok(mut player), Ok(level_root), Ok(mut tilemap)) = (
+        player.get_single_mut(),
+        level_root.get_single(),
+        tilemap.get_single_mut(),
+    ) else {
+        return;
+    };
+
+    // Turn on screen before the events, blocks climb out of the pits they were in there
+    let shown = snapshots.turns.get(*snapshots.time).cloned();
+    let mut restore_turn = None;
+    for ev in history_events.read() {
+        snapshots.time.apply(ev);
+        let now = *snapshots.time;
+        match ev {
+            HistoryEvent::Record => {
+                let snapshot = capture(&snapshots.turns[now - 1], *player, &blocks);
+                snapshots.turns.truncate(now);
+                snapshots.turns.push(snapshot);
+                restore_turn = None;
+            }
+            HistoryEvent::Reset => {
+                let initial = snapshots.turns[0].clone();
+                snapshots.turns.truncate(now);
+                snapshots.turns.push(initial);
+                restore_turn = Some(now);
+            }
+            HistoryEvent::Rewind | HistoryEvent::Redo => restore_turn = Some(now),
+        }
+    }
+
+    if let Some(turn) = restore_turn {
+        restore(
+            &mut cmds,
+            &snapshots.turns[turn],
+            shown.as_ref(),
+            &snapshots.pits,
+            &mut player,
+            &mut blocks,
+            &pit_entities,
+            level_root,
+            &mut tilemap,
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::VecDeque;
+
+use bevy::utils::hashbrown::HashSet;
+
+use super::{
+    level::Level,
+    puzzle::{PuzzleState, StepOutcome},
+    util::CARDINALS_DIR,
+    Dir,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct SolverConfig {
+    /// Maximum amount of distinct board states to visit, the start included, before giving up.
+    /// The won state counts too, so [`SolverReport::explored`] never exceeds it.
+    pub node_budget: usize,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            node_budget: 1_000_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Solution {
+    /// Shortest sequence of moves that wins the level.
+    Winnable(Vec<Dir>),
+    /// Every reachable state was explored and none of them is won.
+    Unwinnable,
+    /// The node budget ran out before a result was found.
+    BudgetExhausted,
+    /// The level can't be simulated, e.g. because it has no player.
+    Invalid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolverReport {
+    pub solution: Solution,
+    /// Amount of distinct board states that were visited.
+    pub explored: usize,
+}
+
+pub fn solve(level: &Level, config: SolverConfig) -> SolverReport {
+    let Some(start) = PuzzleState::from_level(level) else {
+        return SolverReport {
+            solution: Solution::Invalid,
+            explored: 0,
+        };
+    };
+    solve_state(start, config)
+}
+
+/// Breadth first search over all board states reachable from `start`.
+pub fn solve_state(start: PuzzleState, config: SolverConfig) -> SolverReport {
+    if start.is_won() {
+        return SolverReport {
+            solution: Solution::Winnable(Vec::new()),
+            explored: 1,
+        };
+    }
+
+    // For every visited state the index of the state it was reached from and the move taken.
+    let mut parents: Vec<Option<(usize, Dir)>> = vec![None];
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    visited.insert(start.clone());
+    queue.push_back((start, 0));
+
+    while let Some((state, idx)) = queue.pop_front() {
+        for dir in CARDINALS_DIR.iter() {
+            let mut next = state.clone();
+            if next.step(*dir) == StepOutcome::Blocked || visited.contains(&next) {
+                continue;
+            }
+            if parents.len() >= config.node_budget {
+                return SolverReport {
+                    solution: Solution::BudgetExhausted,
+                    explored: parents.len(),
+                };
+            }
+            let next_idx = parents.len();
+            parents.push(Some((idx, *dir)));
+            // States are checked when they are visited, so the last one within the budget counts
+            if next.is_won() {
+                return SolverReport {
+                    solution: Solution::Winnable(moves_to(&parents, next_idx)),
+                    explored: parents.len(),
+                };
+            }
+            visited.insert(next.clone());
+            queue.push_back((next, next_idx));
+        }
+    }
+
+    SolverReport {
+        solution: Solution::Unwinnable,
+        explored: parents.len(),
+    }
+}
+
+fn moves_to(parents: &[Option<(usize, Dir)>], mut idx: usize) -> Vec<Dir> {
+    let mut moves = Vec::new();
+    while let Some((parent, dir)) = parents[idx] {
+        moves.push(dir);
+        idx = parent;
+    }
+    moves.reverse();
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::puzzle::test_level;
+
+    fn budget(node_budget: usize) -> SolverConfig {
+        SolverConfig { node_budget }
+    }
+
+    #[test]
+    fn shortest_solution() {
+        let level = test_level(&["#######", "#_@_bO#", "#######"]);
+        assert_eq!(
+            solve(&level, SolverConfig::default()).solution,
+            Solution::Winnable(vec![Dir::Right, Dir::Right])
+        );
+    }
+
+    #[test]
+    fn already_won() {
+        let level = test_level(&["####", "#@b#", "####"]);
+        assert_eq!(
+            solve(&level, SolverConfig::default()).solution,
+            Solution::Winnable(Vec::new())
+        );
+    }
+
+    #[test]
+    fn budget_exhausted() {
+        let level = test_level(&["#######", "#@__bO#", "#######"]);
+        for limit in [2, 3] {
+            let report = solve(&level, budget(limit));
+            assert_eq!(report.solution, Solution::BudgetExhausted);
+            assert_eq!(report.explored, limit);
+        }
+
+        // The won state is the fourth one visited
+        let report = solve(&level, budget(4));
+        assert_eq!(
+            report.solution,
+            Solution::Winnable(vec![Dir::Right, Dir::Right, Dir::Right])
+        );
+        assert_eq!(report.explored, 4);
+    }
+
+    #[test]
+    fn win_found_before_a_sibling_exhausts_the_budget() {
+        // Pushing into the pit wins, walking left would be one state too many
+        let level = test_level(&["######", "#_@bO#", "######"]);
+        let report = solve(&level, budget(2));
+        assert_eq!(report.solution, Solution::Winnable(vec![Dir::Right]));
+        assert_eq!(report.explored, 2);
+
+        // Only the start fits, the won state doesn't
+        let report = solve(&level, budget(1));
+        assert_eq!(report.solution, Solution::BudgetExhausted);
+        assert_eq!(report.explored, 1);
+    }
+
+    #[test]
+    fn invalid_without_player() {
+        let level = test_level(&["_"]);
+        assert_eq!(
+            solve(&level, SolverConfig::default()).solution,
+            Solution::Invalid
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use bevy::{audio::Volume, log, prelude::*};
+use bevy_asset_loader::prelude::*;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    history::HistoryEvent,
+    mechanics::PitFilled,
+    player::{PlayerBumped, PlayerMoved},
+    save::{SaveData, SavePath},
+    win::LevelCompleted,
+    GameAction, GameState,
+};
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SoundEffect>()
+            .register_type::<SoundSettings>()
+            .add_systems(
+                Update,
+                (
+                    // The keys are being rebound there
+                    sound_settings.run_if(not(in_state(GameState::Settings))),
+                    (game_sounds, play_sounds)
+                        .chain()
+                        .run_if(resource_exists::<SoundAssets>),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, AssetCollection, Debug)]
+pub struct SoundAssets {
+    #[asset(path = "sounds/step.wav")]
+    pub step: Handle<AudioSource>,
+    #[asset(path = "sounds/push.wav")]
+    pub push: Handle<AudioSource>,
+    #[asset(path = "sounds/pull.wav")]
+    pub pull: Handle<AudioSource>,
+    #[asset(path = "sounds/bump.wav")]
+    pub bump: Handle<AudioSource>,
+    #[asset(path = "sounds/pit.wav")]
+    pub pit: Handle<AudioSource>,
+    #[asset(path = "sounds/undo.wav")]
+    pub undo: Handle<AudioSource>,
+    #[asset(path = "sounds/reset.wav")]
+    pub reset: Handle<AudioSource>,
+    #[asset(path = "sounds/complete.wav")]
+    pub complete: Handle<AudioSource>,
+}
+
+/// Plays a sound, game events are turned into these by [`game_sounds`].
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundEffect {
+    Step,
+    Push,
+    Pull,
+    /// Walked into something that doesn't move.
+    Bump,
+    PitFilled,
+    Undo,
+    Redo,
+    Reset,
+    LevelCompleted,
+}
+
+impl SoundEffect {
+    fn source(self, assets: &SoundAssets) -> &Handle<AudioSource> {
+        match self {
+            SoundEffect::Step => &assets.step,
+            SoundEffect::Push => &assets.push,
+            SoundEffect::Pull => &assets.pull,
+            SoundEffect::Bump => &assets.bump,
+            SoundEffect::PitFilled => &assets.pit,
+            SoundEffect::Undo | SoundEffect::Redo => &assets.undo,
+            SoundEffect::Reset => &assets.reset,
+            SoundEffect::LevelCompleted => &assets.complete,
+        }
+    }
+
+    /// Redo sounds like a higher pitched undo.
+    fn speed(self) -> f32 {
+        match self {
+            SoundEffect::Redo => 1.25,
+            _ => 1.,
+        }
+    }
+}
+
+/// Stored in the [`SaveData`] so it survives restarts.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Reflect)]
+#[serde(default)]
+pub struct SoundSettings {
+    /// Between 0 and 1.
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        SoundSettings {
+            volume: 0.7,
+            muted: false,
+        }
+    }
+}
+
+impl SoundSettings {
+    const VOLUME_STEP: f32 = 0.1;
+
+    /// Volume sounds are played at, zero when muted.
+    pub fn effective_volume(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.volume.clamp(0., 1.)
+        }
+    }
+}
+
+fn sound_settings(
+    actions: Query<&ActionState<GameAction>>,
+    mut save: ResMut<SaveData>,
+    save_path: Res<SavePath>,
+) {
+    let Ok(actions) = actions.get_single() else {
+        return;
+    };
+
+    let mut settings = save.sound;
+    if actions.just_pressed(&GameAction::ToggleMute) {
+        settings.muted = !settings.muted;
+    }
+    if actions.just_pressed(&GameAction::VolumeDown) {
+        settings.volume = (settings.volume - SoundSettings::VOLUME_STEP).max(0.);
+    }
+    if actions.just_pressed(&GameAction::VolumeUp) {
+        settings.volume = (settings.volume + SoundSettings::VOLUME_STEP).min(1.);
+        settings.muted = false;
+    }
+    if settings == save.sound {
+        return;
+    }
+    log::info!(
+        "Volume {:.0}%{}",
+        settings.volume * 100.,
+        if settings.muted { ", muted" } else { "" }
+    );
+    save.sound = settings;
+    if let Some(path) = save_path.0.as_ref() {
+        save.write(path);
+    }
+}
+
+fn game_sounds(
+    mut sounds: EventWriter<SoundEffect>,
+    mut player_moved: EventReader<PlayerMoved>,
+    mut player_bumped: EventReader<PlayerBumped>,
+    mut pit_filled: EventReader<PitFilled>,
+    mut history_events: EventReader<HistoryEvent>,
+    mut level_completed: EventReader<LevelCompleted>,
+) {
+    for moved in player_moved.read() {
+        sounds.send(match (moved.pushed, moved.pulled) {
+            (true, _) => SoundEffect::Push,
+            (false, true) => SoundEffect::Pull,
+            (false, false) => SoundEffect::Step,
+        });
+    }
+    if player_bumped.read().count() > 0 {
+        sounds.send(SoundEffect::Bump);
+    }
+    if pit_filled.read().count() > 0 {
+        sounds.send(SoundEffect::PitFilled);
+    }
+    // Jumping through the timeline sends many events at once, they make a single sound
+    let history_sound = history_events
+        .read()
+        .filter_map(|ev| match ev {
+            HistoryEvent::Record => None,
+            HistoryEvent::Rewind => Some(SoundEffect::Undo),
+            HistoryEvent::Redo => Some(SoundEffect::Redo),
+            HistoryEvent::Reset => Some(SoundEffect::Reset),
+        })
+        .last();
+    if let Some(sound) = history_sound {
+        sounds.send(sound);
+    }
+    if level_completed.read().count() > 0 {
+        sounds.send(SoundEffect::LevelCompleted);
+    }
+}
+
+fn play_sounds(
+    mut cmds: Commands,
+    mut sounds: EventReader<SoundEffect>,
+    assets: Res<SoundAssets>,
+    save: Res<SaveData>,
+) {
+    let volume = save.sound.effective_volume();
+    if volume <= 0. {
+        sounds.clear();
+        return;
+    }
+    for sound in sounds.read() {
+        cmds.spawn((
+            AudioBundle {
+                source: sound.source(&assets).clone_weak(),
+                settings: PlaybackSettings::DESPAWN
+                    .with_volume(Volume::new(volume))
+                    .with_speed(sound.speed()),
+            },
+            Name::new("Sound"),
+        ));
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+
+use crate::cleanup::DependOnState;
+
+use super::{
+    history::{CurrentTime, HistoryEvent, JumpToTurn},
+    mechanics::despawn_on_pit,
+    player::PlayerInput,
+    EntityKind, GameState,
+};
+
+pub struct TimelinePlugin;
+
+impl Plugin for TimelinePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Timeline>()
+            .add_systems(
+                OnTransition {
+                    exited: GameState::LevelTransition,
+                    entered: GameState::Play,
+                },
+                (reset_timeline, spawn_timeline),
+            )
+            .add_systems(
+                Update,
+                (
+                    scrub.before(PlayerInput),
+                    (record_turns.after(despawn_on_pit), update_timeline).chain(),
+                )
+                    .run_if(in_state(GameState::Play)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnKind {
+    Start,
+    Move,
+    Reset,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Turn {
+    pub kind: TurnKind,
+    /// Pits that are still open after the turn.
+    pub open_pits: usize,
+}
+
+impl Turn {
+    fn color(&self, previous: Option<&Turn>) -> Color {
+        match self.kind {
+            TurnKind::Start => Color::srgb(0.4, 0.8, 0.4),
+            TurnKind::Reset => Color::srgb(0.9, 0.5, 0.2),
+            TurnKind::Move if previous.is_some_and(|prev| prev.open_pits > self.open_pits) => {
+                Color::srgb(0.3, 0.5, 0.9)
+            }
+            TurnKind::Move => Color::srgb(0.6, 0.6, 0.6),
+        }
+    }
+}
+
+/// Every turn that can be reached with undo and redo, turn 0 is the start of the level.
+#[derive(Resource, Default, Debug)]
+pub struct Timeline {
+    pub turns: Vec<Turn>,
+    /// Follows [`CurrentTime`] event by event, which is only updated once per frame.
+    time: CurrentTime,
+}
+
+#[derive(Component)]
+struct TimelineStrip;
+
+#[derive(Component, Clone, Copy)]
+struct TimelineTurn(usize);
+
+fn reset_timeline(mut timeline: ResMut<Timeline>) {
+    *timeline = Timeline::default();
+}
+
+fn spawn_timeline(mut cmds: Commands) {
+    cmds.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(10.),
+                left: Val::Percent(10.),
+                width: Val::Percent(80.),
+                height: Val::Px(16.),
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(1.),
+                ..default()
+            },
+            ..default()
+        },
+        TimelineStrip,
+        DependOnState::single(GameState::Play),
+        Name::new("Timeline"),
+    ));
+}
+
+fn record_turns(
+    mut timeline: ResMut<Timeline>,
+    mut history_events: EventReader<HistoryEvent>,
+    kinds: Query<&EntityKind>,
+) {
+    let open_pits = kinds
+        .iter()
+        .filter(|kind| matches!(kind, EntityKind::Pit))
+        .count();
+    if timeline.turns.is_empty() {
+        timeline.turns.push(Turn {
+            kind: TurnKind::Start,
+            open_pits,
+        });
+    }
+
+    for ev in history_events.read() {
+        timeline.time.apply(ev);
+        let kind = match ev {
+            HistoryEvent::Record => TurnKind::Move,
+            HistoryEvent::Reset => TurnKind::Reset,
+            HistoryEvent::Rewind | HistoryEvent::Redo => continue,
+        };
+        let now = *timeline.time;
+        timeline.turns.truncate(now);
+        timeline.turns.push(Turn { kind, open_pits });
+    }
+}
+
+fn update_timeline(
+    mut cmds: Commands,
+    timeline: Res<Timeline>,
+    current_time: Res<CurrentTime>,
+    strip: Query<(Entity, Option<&Children>), With<TimelineStrip>>,
+    mut turns: Query<(&TimelineTurn, &mut BackgroundColor, &mut Style)>,
+) {
+    let Ok((strip, children)) = strip.get_single() else {
+        return;
+    };
+    if children.map_or(0, |children| children.len()) != timeline.turns.len() {
+        cmds.entity(strip).despawn_descendants();
+        cmds.entity(strip).with_children(|parent| {
+            for idx in 0..timeline.turns.len() {
+                parent.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            flex_grow: 1.,
+                            max_width: Val::Px(12.),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    TimelineTurn(idx),
+                ));
+            }
+        });
+        // Colored once the turns exist
+        return;
+    }
+
+    for (TimelineTurn(idx), mut color, mut style) in turns.iter_mut() {
+        let turn = &timeline.turns[*idx];
+        let mut turn_color = turn.color(idx.checked_sub(1).map(|prev| &timeline.turns[prev]));
+        if *idx > **current_time {
+            // Turns that can be redone
+            turn_color = turn_color.with_alpha(0.3);
+        }
+        *color = turn_color.into();
+        style.height = if *idx == **current_time {
+            Val::Percent(100.)
+        } else {
+            Val::Percent(50.)
+        };
+    }
+}
+
+/// Jumps to the turn that is clicked, or hovered while the mouse is held after clicking one.
+fn scrub(
+    mut scrubbing: Local<bool>,
+    mut jumps: EventWriter<JumpToTurn>,
+    turns: Query<(&Interaction, &TimelineTurn)>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    current_time: Res<CurrentTime>,
+) {
+    if !mouse.pressed(MouseButton::Left) {
+        *scrubbing = false;
+        return;
+    }
+
+    let turn_with = |wanted: Interaction| {
+        turns
+            .iter()
+            .find(|(interaction, _)| **interaction == wanted)
+            .map(|(_, TimelineTurn(idx))| *idx)
+    };
+    // The clicked turn stays pressed while the mouse is held, even when it is no longer hovered
+    let pressed = turn_with(Interaction::Pressed);
+    if pressed.is_some() {
+        *scrubbing = true;
+    }
+    let hovered = turn_with(Interaction::Hovered).filter(|_| *scrubbing);
+    let target = hovered.or(pressed);
+    if let Some(turn) = target.filter(|turn| *turn != **current_time) {
+        jumps.send(JumpToTurn(turn));
+    }
+}
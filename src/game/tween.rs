@@ -0,0 +1,137 @@
+//! Visual interpolation of tile moves, the logical [`TilePos`] always changes right away.
+
+use std::time::Duration;
+
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::cleanup::DependOnState;
+
+use super::{copy_pos_to_transform, GameState, TilePos};
+
+pub struct TweenPlugin;
+
+impl Plugin for TweenPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<FromPit>().add_systems(
+            PostUpdate,
+            (animate_moves, animate_scales)
+                .after(copy_pos_to_transform)
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+fn ease_out(t: f32) -> f32 {
+    1. - (1. - t).powi(2)
+}
+
+fn ease_in(t: f32) -> f32 {
+    t * t
+}
+
+/// Slides the translation from `from` to `to`.
+#[derive(Component, Debug, Clone)]
+pub struct MoveTween {
+    pub from: Vec2,
+    pub to: Vec2,
+    pub timer: Timer,
+}
+
+impl MoveTween {
+    pub fn new(from: Vec2, to: Vec2, duration: Duration) -> Self {
+        MoveTween {
+            from,
+            to,
+            timer: Timer::new(duration, TimerMode::Once),
+        }
+    }
+}
+
+/// Scales uniformly from `from` to `to`, used for blocks sinking into and rising out of pits.
+#[derive(Component, Debug, Clone)]
+pub struct ScaleTween {
+    pub from: f32,
+    pub to: f32,
+    pub timer: Timer,
+    /// Despawns the entity once the tween is done.
+    pub despawn: bool,
+}
+
+impl ScaleTween {
+    pub fn new(from: f32, to: f32, duration: Duration) -> Self {
+        ScaleTween {
+            from,
+            to,
+            timer: Timer::new(duration, TimerMode::Once),
+            despawn: false,
+        }
+    }
+}
+
+/// A block that was spawned back out of the pit at the given position, it rises out of it.
+#[derive(Component, Reflect, Debug, Clone, Copy)]
+pub struct FromPit(pub TilePos);
+
+/// Bundle of a sprite that slides into a pit and shrinks away, the block itself is already gone.
+pub fn sinking_sprite(
+    texture: Handle<Image>,
+    transform: Transform,
+    pit: Vec2,
+    duration: Duration,
+) -> impl Bundle {
+    (
+        SpriteBundle {
+            texture,
+            transform,
+            ..default()
+        },
+        MoveTween::new(transform.translation.truncate(), pit, duration),
+        ScaleTween {
+            despawn: true,
+            ..ScaleTween::new(1., 0., duration * 2)
+        },
+        DependOnState(vec![GameState::Play, GameState::Won]),
+        Name::new("Sinking Block"),
+    )
+}
+
+fn animate_moves(
+    mut cmds: Commands,
+    mut tweens: Query<(Entity, &mut MoveTween, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut tween, mut transform) in tweens.iter_mut() {
+        tween.timer.tick(time.delta());
+        let pos = tween.from.lerp(tween.to, ease_out(tween.timer.fraction()));
+        transform.translation = pos.extend(transform.translation.z);
+        if tween.timer.finished() {
+            cmds.entity(entity).remove::<MoveTween>();
+        }
+    }
+}
+
+fn animate_scales(
+    mut cmds: Commands,
+    mut tweens: Query<(Entity, &mut ScaleTween, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut tween, mut transform) in tweens.iter_mut() {
+        tween.timer.tick(time.delta());
+        let t = tween.timer.fraction();
+        // Sinking speeds up towards the end, rising slows down
+        let t = if tween.to < tween.from {
+            ease_in(t)
+        } else {
+            ease_out(t)
+        };
+        transform.scale = Vec3::splat(tween.from + (tween.to - tween.from) * t);
+        if !tween.timer.finished() {
+            continue;
+        }
+        if tween.despawn {
+            cmds.entity(entity).despawn_recursive();
+        } else {
+            cmds.entity(entity).remove::<ScaleTween>();
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, window::PrimaryWindow};
 
 use super::Dir;
 
@@ -22,3 +22,22 @@ pub const DIRS: [IVec2; 8] = [
     IVec2::NEG_X,
     IVec2::new(-1, 1),
 ];
+
+/// Tile at a position in the viewport, such as the cursor or a touch.
+pub fn viewport_tile(
+    camera: &Query<(&Camera, &GlobalTransform)>,
+    viewport_pos: Vec2,
+) -> Option<IVec2> {
+    let (camera, camera_transform) = camera.get_single().ok()?;
+    let world = camera.viewport_to_world_2d(camera_transform, viewport_pos)?;
+
+    Some((world / 16.).round().as_ivec2())
+}
+
+pub fn cursor_tile(
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+) -> Option<IVec2> {
+    let cursor = window.get_single().ok()?.cursor_position()?;
+    viewport_tile(&camera, cursor)
+}
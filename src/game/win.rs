@@ -0,0 +1,196 @@
+use bevy::{log, prelude::*};
+
+use crate::{cleanup::DependOnState, ui::NineSliceButtonText};
+
+use super::{
+    history::{CurrentTime, HandleHistoryEvents, HistoryEvent},
+    level::{LevelData, LevelId},
+    level_select::CurrentLevel,
+    GameAssets, GameState,
+};
+
+const CURRENT_STATE: GameState = GameState::Won;
+
+pub struct WinPlugin;
+
+impl Plugin for WinPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LevelCompleted>()
+            .init_resource::<LevelStats>()
+            .register_type::<LevelStats>()
+            .add_systems(
+                OnTransition {
+                    exited: GameState::LevelTransition,
+                    entered: GameState::Play,
+                },
+                reset_stats,
+            )
+            .add_systems(
+                Update,
+                (
+                    count_moves
+                        .before(HandleHistoryEvents)
+                        .run_if(in_state(GameState::Play)),
+                    record_completion.run_if(on_event::<LevelCompleted>()),
+                ),
+            )
+            .add_systems(OnEnter(CURRENT_STATE), spawn_win_overlay)
+            .add_systems(Update, handle_buttons.run_if(in_state(CURRENT_STATE)));
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct LevelCompleted {
+    pub level: LevelId,
+    pub moves: usize,
+    pub undos: usize,
+    /// Whether the level was completed by a [`Replay`](super::replay::Replay).
+    pub replayed: bool,
+}
+
+/// Statistics of the current attempt at a level.
+#[derive(Resource, Reflect, Default, Debug, Clone, Copy)]
+#[reflect(Resource)]
+pub struct LevelStats {
+    pub moves: usize,
+    pub undos: usize,
+}
+
+fn reset_stats(mut stats: ResMut<LevelStats>) {
+    *stats = LevelStats::default();
+}
+
+fn count_moves(
+    mut history_events: EventReader<HistoryEvent>,
+    mut stats: ResMut<LevelStats>,
+    current_time: Res<CurrentTime>,
+) {
+    for ev in history_events.read() {
+        match ev {
+            HistoryEvent::Record => stats.moves += 1,
+            // Undoing at the start of a level doesn't do anything
+            HistoryEvent::Rewind if **current_time > 0 => stats.undos += 1,
+            HistoryEvent::Redo if current_time.can_redo() => stats.moves += 1,
+            _ => {}
+        }
+    }
+}
+
+fn record_completion(
+    mut level_completed: EventReader<LevelCompleted>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for ev in level_completed.read() {
+        log::info!(
+            "Completed level {} with {} moves and {} undos",
+            ev.level,
+            ev.moves,
+            ev.undos
+        );
+        next_state.set(CURRENT_STATE);
+    }
+}
+
+#[derive(Component, Clone)]
+enum WinButton {
+    NextLevel(LevelId),
+    LevelSelect,
+}
+
+impl From<WinButton> for String {
+    fn from(value: WinButton) -> Self {
+        match value {
+            WinButton::NextLevel(_) => "Next level",
+            WinButton::LevelSelect => "Level select",
+        }
+        .to_string()
+    }
+}
+
+fn spawn_win_overlay(
+    mut cmds: Commands,
+    level_data: LevelData,
+    stats: Res<LevelStats>,
+    assets: Res<GameAssets>,
+) {
+    let button_style = Style {
+        width: Val::Px(200.0),
+        height: Val::Px(60.0),
+        margin: UiRect::all(Val::Px(10.)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        border: UiRect::all(Val::Px(2.)),
+        ..default()
+    };
+    let text_style = TextStyle {
+        font_size: 28.,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    let overlay = cmds
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                background_color: Color::srgba(0., 0., 0., 0.5).into(),
+                ..default()
+            },
+            DependOnState::single(CURRENT_STATE),
+            Name::new("Win Overlay"),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Level complete",
+                TextStyle {
+                    font_size: 48.,
+                    ..text_style.clone()
+                },
+            ));
+            let mut summary = format!("Moves: {}  Undos: {}", stats.moves, stats.undos);
+            if let Some(par) = level_data.current_level_data().meta.par_moves {
+                summary.push_str(&format!("  Par: {}", par));
+            }
+            parent.spawn(TextBundle::from_section(summary, text_style));
+        })
+        .id();
+
+    let mut buttons = vec![WinButton::LevelSelect];
+    if let Some(next) = level_data.next_level() {
+        buttons.insert(0, WinButton::NextLevel(next.id.clone()));
+    }
+    for button in buttons {
+        cmds.add(NineSliceButtonText {
+            button,
+            style: button_style.clone(),
+            texture: assets.button.clone_weak(),
+            parent: overlay,
+            disabled: false,
+        });
+    }
+}
+
+fn handle_buttons(
+    mut game_state: ResMut<NextState<GameState>>,
+    buttons: Query<(&WinButton, &Interaction), Changed<Interaction>>,
+    mut current_level: ResMut<CurrentLevel>,
+) {
+    buttons
+        .iter()
+        .for_each(|(button, interaction)| match (button, interaction) {
+            (WinButton::NextLevel(next), Interaction::Pressed) => {
+                **current_level = next.clone();
+                game_state.set(GameState::LevelTransition);
+            }
+            (WinButton::LevelSelect, Interaction::Pressed) => {
+                game_state.set(GameState::LevelSelect);
+            }
+            _ => {}
+        });
+}
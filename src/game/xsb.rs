@@ -0,0 +1,200 @@
+//! Loader for levels in the standard XSB format used by classic Sokoban collections.
+//!
+//! Goals have no meaning in this game, they are dropped and whatever stands on them is kept.
+//! Floor outside of the walls becomes wall, so the level is closed off like ours are.
+
+use std::future::Future;
+
+use bevy::{
+    asset::{AssetLoader, AsyncReadExt},
+    utils::ConditionalSendFuture,
+};
+
+use super::{
+    level::{LevelLoaderError, LevelMeta, Levels, StringLevel, StringLevels},
+    util::CARDINALS,
+};
+
+#[derive(Default)]
+pub struct XsbLoader;
+
+impl AssetLoader for XsbLoader {
+    type Asset = Levels;
+    type Settings = ();
+    type Error = LevelLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut bevy::asset::LoadContext,
+    ) -> impl ConditionalSendFuture
+           + Future<Output = Result<<Self as AssetLoader>::Asset, <Self as AssetLoader>::Error>>
+    {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            parse_xsb(std::str::from_utf8(&bytes)?).to_levels()
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["xsb", "sok"]
+    }
+}
+
+/// Maps an XSB glyph to ours, `None` for floor which is resolved later.
+fn convert_glyph(glyph: char) -> Option<Option<char>> {
+    match glyph {
+        '#' => Some(Some('#')),
+        '@' | '+' | 'p' | 'P' => Some(Some('@')),
+        '$' | '*' | 'b' | 'B' => Some(Some('b')),
+        ' ' | '-' | '_' | '.' => Some(None),
+        _ => None,
+    }
+}
+
+fn is_board_line(line: &str) -> bool {
+    line.contains('#') && line.chars().all(|glyph| convert_glyph(glyph).is_some())
+}
+
+/// Value of a `Key: value` line following a board.
+fn field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let (name, value) = line.split_once(':')?;
+    name.trim()
+        .eq_ignore_ascii_case(key)
+        .then(|| value.trim())
+        .filter(|value| !value.is_empty())
+}
+
+/// Splits an XSB file into its levels and converts them into our glyphs.
+///
+/// A level is titled by a `Title:` line after its board, or else by the line right above it.
+pub fn parse_xsb(text: &str) -> StringLevels {
+    let mut levels = Vec::new();
+    let mut lines = text.lines().map(str::trim_end).peekable();
+    let mut previous: Option<&str> = None;
+    while let Some(line) = lines.next() {
+        if !is_board_line(line) {
+            previous = Some(line).filter(|line| !line.trim().is_empty());
+            continue;
+        }
+
+        let mut board = vec![line];
+        while let Some(line) = lines.next_if(|line| is_board_line(line)) {
+            board.push(line);
+        }
+        let mut meta = LevelMeta {
+            name: previous
+                .take()
+                .map(|line| line.trim_start_matches(';').trim())
+                .map(|line| field(line, "Title").unwrap_or(line).to_string())
+                .filter(|name| !name.is_empty()),
+            ..Default::default()
+        };
+        // Fields end at the first empty line or the next board
+        while let Some(line) = lines.next_if(|line| !line.trim().is_empty() && !is_board_line(line))
+        {
+            if let Some(title) = field(line, "Title") {
+                meta.name = Some(title.to_string());
+            } else if let Some(author) = field(line, "Author") {
+                meta.author = Some(author.to_string());
+            }
+        }
+
+        levels.push(StringLevel {
+            layout: convert_board(&board).join("\n"),
+            meta,
+        });
+    }
+    StringLevels(levels)
+}
+
+/// Pads ragged rows and walls in everything that can be reached from outside the board.
+fn convert_board(board: &[&str]) -> Vec<String> {
+    let width = board
+        .iter()
+        .map(|row| row.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut tiles = board
+        .iter()
+        .map(|row| {
+            let mut tiles = row
+                .chars()
+                .map(|glyph| convert_glyph(glyph).flatten())
+                .collect::<Vec<Option<char>>>();
+            tiles.resize(width, None);
+            tiles
+        })
+        .collect::<Vec<_>>();
+
+    let height = tiles.len();
+    let mut outside = (0..height)
+        .flat_map(|y| [(0, y), (width - 1, y)])
+        .chain((0..width).flat_map(|x| [(x, 0), (x, height - 1)]))
+        .collect::<Vec<(usize, usize)>>();
+    while let Some((x, y)) = outside.pop() {
+        if tiles[y][x].is_some() {
+            continue;
+        }
+        tiles[y][x] = Some('#');
+        for dir in CARDINALS {
+            let (nx, ny) = (x as i32 + dir.x, y as i32 + dir.y);
+            if nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height {
+                outside.push((nx as usize, ny as usize));
+            }
+        }
+    }
+
+    tiles
+        .into_iter()
+        .map(|row| row.into_iter().map(|tile| tile.unwrap_or('_')).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "Level one
+ #####
+##@$.#
+#####
+Title: First
+Author: Someone
+
+; Second
+####
+#+*#
+####
+";
+
+    #[test]
+    fn converts_boards() {
+        let levels = parse_xsb(TEXT);
+        let layouts = levels
+            .iter()
+            .map(|level| level.layout.as_str())
+            .collect::<Vec<_>>();
+        // Goals are dropped, the floor outside of the walls is walled in
+        assert_eq!(layouts, ["######\n##@b_#\n######", "####\n#@b#\n####"]);
+        for (index, level) in levels.iter().enumerate() {
+            assert!(level.to_level(index).is_ok());
+        }
+    }
+
+    #[test]
+    fn reads_titles_and_authors() {
+        let levels = parse_xsb(TEXT);
+        assert_eq!(levels[0].meta.name.as_deref(), Some("First"));
+        assert_eq!(levels[0].meta.author.as_deref(), Some("Someone"));
+        assert_eq!(levels[1].meta.name.as_deref(), Some("Second"));
+        assert_eq!(levels[1].meta.author, None);
+    }
+
+    #[test]
+    fn ignores_text_without_boards() {
+        assert!(parse_xsb("Only a comment\n\nAuthor: Nobody").is_empty());
+    }
+}
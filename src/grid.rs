@@ -1,7 +1,7 @@
 use bevy::{math::*, reflect::Reflect};
 use std::ops::{Index, IndexMut};
 
-#[derive(Debug, Clone, Reflect)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Reflect)]
 pub struct Grid<T> {
     cells: Vec<T>,
     size: IVec2, // TODO: use u32
@@ -37,7 +37,7 @@ impl<T> Grid<T> {
     }
 
     fn idx(&self, pos: IVec2) -> Option<usize> {
-        if pos.x < self.size.x && pos.y < self.size.y {
+        if pos.x >= 0 && pos.y >= 0 && pos.x < self.size.x && pos.y < self.size.y {
             Some((pos.y * self.size.x + pos.x) as usize)
         } else {
             None
@@ -0,0 +1,5 @@
+pub mod cleanup;
+pub mod event_scheduler;
+pub mod game;
+pub mod grid;
+pub mod ui;
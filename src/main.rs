@@ -1,11 +1,6 @@
 use bevy::prelude::*;
 use bevy_pancam::DirectionKeys;
-
-pub mod cleanup;
-pub mod event_scheduler;
-pub mod game;
-pub mod grid;
-pub mod ui;
+use lost::game;
 
 fn main() {
     let mut app = App::new();
@@ -7,34 +7,39 @@ pub struct NineSliceButtonText<T: Component + Into<String> + Clone> {
     pub style: Style,
     pub texture: Handle<Image>,
     pub parent: Entity,
+    /// Disabled buttons are greyed out and can't be interacted with.
+    pub disabled: bool,
 }
 
 impl<T: Component + Into<String> + Clone> Command for NineSliceButtonText<T> {
     fn apply(self, world: &mut World) {
         world.entity_mut(self.parent).with_children(|parent| {
-            parent
-                .spawn((
-                    NineSliceUiMaterialBundle {
-                        style: self.style,
-                        nine_slice_texture: NineSliceUiTexture::from_image(
-                            self.texture.clone_weak(),
-                        ),
-                        focus_policy: bevy::ui::FocusPolicy::Block,
+            let mut button = parent.spawn((
+                NineSliceUiMaterialBundle {
+                    style: self.style,
+                    nine_slice_texture: NineSliceUiTexture::from_image(self.texture.clone_weak()),
+                    focus_policy: bevy::ui::FocusPolicy::Block,
+                    ..default()
+                },
+                self.button.clone(),
+            ));
+            if !self.disabled {
+                button.insert(Interaction::default());
+            }
+            button.with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    self.button.into(),
+                    TextStyle {
+                        font_size: 20.,
+                        color: if self.disabled {
+                            Color::srgb(0.5, 0.5, 0.5)
+                        } else {
+                            Color::WHITE
+                        },
                         ..default()
                     },
-                    Interaction::default(),
-                    self.button.clone(),
-                ))
-                .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        self.button.into(),
-                        TextStyle {
-                            font_size: 20.,
-                            color: Color::WHITE,
-                            ..default()
-                        },
-                    ));
-                });
+                ));
+            });
         });
     }
 }